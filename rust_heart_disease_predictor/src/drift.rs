@@ -0,0 +1,422 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use crate::error::{PredictorError, Result};
+use crate::preprocessing::Dataset;
+use crate::preprocessing::scaling::quantile;
+use crate::preprocessing::schema::FeatureKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DriftVerdict {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Limits at which a drift score turns into a warning or a failure. The
+/// defaults are the usual PSI rule of thumb and 5% / 1% significance levels
+/// for the Kolmogorov–Smirnov and chi-square tests.
+#[derive(Debug, Clone, Copy)]
+pub struct DriftThresholds {
+    pub psi_warn: f32,
+    pub psi_fail: f32,
+    pub p_value_warn: f32,
+    pub p_value_fail: f32,
+}
+
+impl Default for DriftThresholds {
+    fn default() -> Self {
+        DriftThresholds {
+            psi_warn: 0.1,
+            psi_fail: 0.25,
+            p_value_warn: 0.05,
+            p_value_fail: 0.01,
+        }
+    }
+}
+
+/// Training distribution of one feature, stored as summary statistics rather
+/// than patient values.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Distribution {
+    /// Percentiles 0 to 100 for the Kolmogorov–Smirnov test, and the share of
+    /// values in each decile bin for the PSI. `bin_edges` are the inner edges.
+    Continuous {
+        percentiles: Vec<f32>,
+        bin_edges: Vec<f32>,
+        bin_proportions: Vec<f32>,
+    },
+    /// Share of each code seen in training.
+    Discrete { categories: Vec<f32>, proportions: Vec<f32> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureReference {
+    pub name: String,
+    pub kind: FeatureKind,
+    /// Number of non-missing training values.
+    pub count: usize,
+    pub distribution: Distribution,
+}
+
+/// Per-feature statistics of a training set, kept so that later batches can
+/// be compared with it. Continuous features use PSI and Kolmogorov–Smirnov;
+/// categorical and ordinal features use a chi-square test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DriftReference {
+    pub features: Vec<FeatureReference>,
+}
+
+/// Drift scores of one feature. Only the scores that apply to its kind are set.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureDrift {
+    pub name: String,
+    pub kind: FeatureKind,
+    pub psi: Option<f32>,
+    pub ks_statistic: Option<f32>,
+    pub chi_square: Option<f32>,
+    /// p-value of the Kolmogorov–Smirnov or chi-square test.
+    pub p_value: Option<f32>,
+    pub verdict: DriftVerdict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub records: usize,
+    pub features: Vec<FeatureDrift>,
+    /// The worst verdict of any feature.
+    pub verdict: DriftVerdict,
+}
+
+// Proportions of zero would make the PSI infinite.
+const MIN_PROPORTION: f32 = 1e-4;
+
+fn present_values(dataset: &Dataset, column: usize) -> Vec<f32> {
+    let mut values: Vec<f32> = dataset
+        .records
+        .iter()
+        .map(|record| record.features[column])
+        .filter(|value| !value.is_nan())
+        .collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    values
+}
+
+fn bin_proportions(sorted: &[f32], edges: &[f32]) -> Vec<f32> {
+    let mut counts = vec![0usize; edges.len() + 1];
+    for &value in sorted {
+        counts[edges.partition_point(|&edge| edge < value)] += 1;
+    }
+    counts.iter().map(|&count| count as f32 / sorted.len() as f32).collect()
+}
+
+// Reference CDF at `x`, interpolated between percentiles. With `inclusive`
+// false it is the limit from the left, which differs at repeated values.
+fn reference_cdf(percentiles: &[f32], x: f32, inclusive: bool) -> f32 {
+    let i = if inclusive {
+        percentiles.partition_point(|&p| p <= x)
+    } else {
+        percentiles.partition_point(|&p| p < x)
+    };
+    if i == 0 {
+        return 0.0;
+    }
+    if i == percentiles.len() {
+        return 1.0;
+    }
+    let (lower, upper) = (percentiles[i - 1], percentiles[i]);
+    let fraction = if upper > lower { (x - lower) / (upper - lower) } else { 0.0 };
+    ((i - 1) as f32 + fraction) / (percentiles.len() - 1) as f32
+}
+
+fn ks_statistic(percentiles: &[f32], sorted: &[f32]) -> f32 {
+    let m = sorted.len() as f32;
+    percentiles
+        .iter()
+        .chain(sorted.iter())
+        .map(|&x| {
+            let below = sorted.partition_point(|&v| v < x) as f32 / m;
+            let at_or_below = sorted.partition_point(|&v| v <= x) as f32 / m;
+            let left = (reference_cdf(percentiles, x, false) - below).abs();
+            let right = (reference_cdf(percentiles, x, true) - at_or_below).abs();
+            left.max(right)
+        })
+        .fold(0.0, f32::max)
+}
+
+// Asymptotic p-value of the two-sample Kolmogorov–Smirnov statistic.
+fn ks_p_value(statistic: f32, n: usize, m: usize) -> f32 {
+    let effective = (n * m) as f64 / (n + m) as f64;
+    let lambda = (effective.sqrt() + 0.12 + 0.11 / effective.sqrt()) * statistic as f64;
+    if lambda < 1e-3 {
+        return 1.0;
+    }
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let term = (-2.0 * (k * k) as f64 * lambda * lambda).exp();
+        sum += if k % 2 == 1 { term } else { -term };
+        if term < 1e-10 {
+            break;
+        }
+    }
+    (2.0 * sum).clamp(0.0, 1.0) as f32
+}
+
+// Lanczos approximation of ln Γ(x).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.120_865_097_386_617_9e-2,
+        -0.539_523_938_495_3e-5,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let mut series = 1.000_000_000_190_015;
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate() {
+        series += coefficient / (x + 1.0 + i as f64);
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+// Regularised upper incomplete gamma function Q(a, x), by its series below
+// a + 1 and its continued fraction above.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let log_prefix = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..500 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-12 {
+                break;
+            }
+        }
+        1.0 - sum * log_prefix.exp()
+    } else {
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..500 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-12 {
+                break;
+            }
+        }
+        log_prefix.exp() * h
+    }
+}
+
+fn chi_square_p_value(statistic: f32, degrees_of_freedom: usize) -> f32 {
+    if degrees_of_freedom == 0 {
+        return 1.0;
+    }
+    gamma_q(degrees_of_freedom as f64 / 2.0, statistic as f64 / 2.0).clamp(0.0, 1.0) as f32
+}
+
+impl DriftThresholds {
+    fn psi_verdict(&self, psi: f32) -> DriftVerdict {
+        if psi > self.psi_fail {
+            DriftVerdict::Fail
+        } else if psi > self.psi_warn {
+            DriftVerdict::Warn
+        } else {
+            DriftVerdict::Pass
+        }
+    }
+
+    fn p_value_verdict(&self, p_value: f32) -> DriftVerdict {
+        if p_value < self.p_value_fail {
+            DriftVerdict::Fail
+        } else if p_value < self.p_value_warn {
+            DriftVerdict::Warn
+        } else {
+            DriftVerdict::Pass
+        }
+    }
+}
+
+impl DriftReference {
+    /// Records the distribution of every feature of a training set.
+    pub fn fit(dataset: &Dataset) -> Result<Self> {
+        if dataset.is_empty() {
+            return Err(PredictorError::EmptyDataset);
+        }
+
+        let mut features = Vec::with_capacity(dataset.feature_names.len());
+        for (i, (name, &kind)) in dataset.feature_names.iter().zip(dataset.feature_kinds.iter()).enumerate() {
+            let values = present_values(dataset, i);
+            if values.is_empty() {
+                return Err(PredictorError::InvalidInput(format!("feature '{}' has no values", name)));
+            }
+
+            let distribution = match kind {
+                FeatureKind::Continuous => {
+                    let percentiles: Vec<f32> = (0..=100).map(|p| quantile(&values, p as f32 / 100.0)).collect();
+                    let mut bin_edges: Vec<f32> = (1..10).map(|d| quantile(&values, d as f32 / 10.0)).collect();
+                    bin_edges.dedup();
+                    let bin_proportions = bin_proportions(&values, &bin_edges);
+                    Distribution::Continuous {
+                        percentiles,
+                        bin_edges,
+                        bin_proportions,
+                    }
+                }
+                FeatureKind::Categorical | FeatureKind::Ordinal => {
+                    let mut categories = values.clone();
+                    categories.dedup();
+                    let proportions = categories
+                        .iter()
+                        .map(|category| values.iter().filter(|&value| value == category).count() as f32 / values.len() as f32)
+                        .collect();
+                    Distribution::Discrete { categories, proportions }
+                }
+            };
+
+            features.push(FeatureReference {
+                name: name.clone(),
+                kind,
+                count: values.len(),
+                distribution,
+            });
+        }
+
+        Ok(DriftReference { features })
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Compares a new batch with the reference, matching features by name.
+    /// Missing values in the batch are ignored.
+    pub fn compare(&self, batch: &Dataset, thresholds: &DriftThresholds) -> Result<DriftReport> {
+        if batch.is_empty() {
+            return Err(PredictorError::EmptyDataset);
+        }
+
+        let mut features = Vec::with_capacity(self.features.len());
+        for reference in &self.features {
+            let column = batch.feature_index(&reference.name).ok_or_else(|| {
+                PredictorError::SchemaMismatch(format!("batch has no feature '{}'", reference.name))
+            })?;
+            let values = present_values(batch, column);
+            if values.is_empty() {
+                return Err(PredictorError::InvalidInput(format!("feature '{}' has no values in the batch", reference.name)));
+            }
+
+            let drift = match &reference.distribution {
+                Distribution::Continuous {
+                    percentiles,
+                    bin_edges,
+                    bin_proportions: expected,
+                } => {
+                    let actual = bin_proportions(&values, bin_edges);
+                    let psi = expected
+                        .iter()
+                        .zip(actual.iter())
+                        .map(|(&e, &a)| {
+                            let (e, a) = (e.max(MIN_PROPORTION), a.max(MIN_PROPORTION));
+                            (a - e) * (a / e).ln()
+                        })
+                        .sum();
+                    let statistic = ks_statistic(percentiles, &values);
+                    let p_value = ks_p_value(statistic, reference.count, values.len());
+                    FeatureDrift {
+                        name: reference.name.clone(),
+                        kind: reference.kind,
+                        psi: Some(psi),
+                        ks_statistic: Some(statistic),
+                        chi_square: None,
+                        p_value: Some(p_value),
+                        verdict: thresholds.psi_verdict(psi).max(thresholds.p_value_verdict(p_value)),
+                    }
+                }
+                Distribution::Discrete { categories, proportions } => {
+                    // Codes never seen in training share one extra cell
+                    let mut observed = vec![0usize; categories.len() + 1];
+                    for value in &values {
+                        let cell = categories.iter().position(|category| category == value).unwrap_or(categories.len());
+                        observed[cell] += 1;
+                    }
+                    let n = values.len() as f32;
+                    let statistic = observed
+                        .iter()
+                        .zip(proportions.iter().chain(std::iter::once(&0.0)))
+                        .map(|(&o, &p)| {
+                            let expected = p.max(MIN_PROPORTION) * n;
+                            (o as f32 - expected).powi(2) / expected
+                        })
+                        .sum();
+                    let cells = categories.len() + usize::from(observed[categories.len()] > 0);
+                    let p_value = chi_square_p_value(statistic, cells.saturating_sub(1));
+                    FeatureDrift {
+                        name: reference.name.clone(),
+                        kind: reference.kind,
+                        psi: None,
+                        ks_statistic: None,
+                        chi_square: Some(statistic),
+                        p_value: Some(p_value),
+                        verdict: thresholds.p_value_verdict(p_value),
+                    }
+                }
+            };
+            features.push(drift);
+        }
+
+        let verdict = features.iter().map(|feature| feature.verdict).max().unwrap_or(DriftVerdict::Pass);
+        Ok(DriftReport {
+            records: batch.len(),
+            features,
+            verdict,
+        })
+    }
+}
+
+pub fn print_drift_report(report: &DriftReport) {
+    let score = |value: Option<f32>| value.map_or("-".to_string(), |v| format!("{:.4}", v));
+    println!("| {:<10} | PSI      | KS       | Chi-sq   | p-value  | Verdict |", "Feature");
+    println!("|------------|----------|----------|----------|----------|---------|");
+    for feature in &report.features {
+        println!(
+            "| {:<10} | {:<8} | {:<8} | {:<8} | {:<8} | {:<7} |",
+            feature.name,
+            score(feature.psi),
+            score(feature.ks_statistic),
+            score(feature.chi_square),
+            score(feature.p_value),
+            format!("{:?}", feature.verdict)
+        );
+    }
+    println!("|------------|----------|----------|----------|----------|---------|");
+    println!("Overall verdict for {} records: {:?}", report.records, report.verdict);
+}
@@ -1,40 +1,116 @@
-use crate::models::Model;
+use crate::error::{PredictorError, Result};
+use crate::models::{Model, most_probable};
 use crate::preprocessing::ProcessedPatientRecord;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Voting {
+    /// Each model votes for its predicted class.
+    Hard,
+    /// The class with the highest probability averaged over the models wins.
+    Soft,
+}
+
 pub struct VotingClassifier {
     models: Vec<Box<dyn Model>>,
+    voting: Voting,
 }
 
 impl VotingClassifier {
     pub fn new(models: Vec<Box<dyn Model>>) -> Self {
-        VotingClassifier { models }
+        VotingClassifier {
+            models,
+            voting: Voting::Hard,
+        }
+    }
+
+    pub fn with_voting(mut self, voting: Voting) -> Self {
+        self.voting = voting;
+        self
     }
 }
 
 impl Model for VotingClassifier {
-    fn train(&mut self, training_data: &[ProcessedPatientRecord]) {
+    fn train(&mut self, training_data: &[ProcessedPatientRecord]) -> Result<()> {
+        if self.models.is_empty() {
+            return Err(PredictorError::InvalidHyperparameter(
+                "a voting classifier needs at least one model".to_string(),
+            ));
+        }
         for model in &mut self.models {
-            model.train(training_data);
+            model.train(training_data)?;
         }
+        Ok(())
     }
 
-    fn predict(&self, record: &ProcessedPatientRecord) -> u8 {
-        let mut votes = Vec::new();
-        for model in &self.models {
-            votes.push(model.predict(record));
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8> {
+        self.predict_batch(std::slice::from_ref(record)).map(|predictions| predictions[0])
+    }
+
+    /// The average of the models' class probabilities, whichever voting is used.
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>> {
+        self.predict_proba_batch(std::slice::from_ref(record))
+            .map(|mut probabilities| probabilities.remove(0))
+    }
+
+    // Each model predicts the whole batch, so members with a vectorised path use it
+    fn predict_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<u8>> {
+        if self.voting == Voting::Soft {
+            return Ok(self.predict_proba_batch(records)?.iter().map(|p| most_probable(p)).collect());
         }
+        if self.models.is_empty() {
+            return Err(PredictorError::NotFitted("VotingClassifier"));
+        }
+
+        let predictions = self
+            .models
+            .iter()
+            .map(|model| model.predict_batch(records))
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+        Ok((0..records.len())
+            .map(|i| {
+                let votes: Vec<u8> = predictions.iter().map(|model_predictions| model_predictions[i]).collect();
+                hard_vote(&votes)
+            })
+            .collect())
+    }
 
-        let mut vote_counts = std::collections::HashMap::new();
-        for vote in votes.iter() {
-            *vote_counts.entry(vote).or_insert(0) += 1;
+    fn predict_proba_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<Vec<f32>>> {
+        if self.models.is_empty() {
+            return Err(PredictorError::NotFitted("VotingClassifier"));
         }
 
-        // Find the vote with the maximum count.
-        // In case of a tie, the first model's prediction is effectively chosen.
-        vote_counts
-            .into_iter()
-            .max_by_key(|&(_, count)| count)
-            .map(|(val, _)| *val)
-            .unwrap_or_else(|| *votes.first().unwrap_or(&0))
+        let mut averages: Vec<Vec<f32>> = vec![Vec::new(); records.len()];
+        for model in &self.models {
+            for (average, probabilities) in averages.iter_mut().zip(model.predict_proba_batch(records)?) {
+                // Models that never saw the largest classes return shorter vectors
+                if probabilities.len() > average.len() {
+                    average.resize(probabilities.len(), 0.0);
+                }
+                for (total, probability) in average.iter_mut().zip(probabilities) {
+                    *total += probability / self.models.len() as f32;
+                }
+            }
+        }
+        Ok(averages)
+    }
+}
+
+// The class with the most votes. In case of a tie, the earliest model's
+// prediction among the tied classes is chosen.
+fn hard_vote(votes: &[u8]) -> u8 {
+    let mut vote_counts = std::collections::BTreeMap::new();
+    for vote in votes.iter() {
+        *vote_counts.entry(vote).or_insert(0) += 1;
+    }
+
+    let mut best_vote = votes[0];
+    let mut best_count = 0;
+    for vote in votes {
+        let count = vote_counts[vote];
+        if count > best_count {
+            best_vote = *vote;
+            best_count = count;
+        }
     }
+    best_vote
 }
@@ -0,0 +1,81 @@
+use std::fmt;
+
+/// Errors raised anywhere in the crate. Fallible steps return this instead of
+/// falling back to a default value, so bad input never becomes a silent prediction.
+#[derive(Debug)]
+pub enum PredictorError {
+    Io(std::io::Error),
+    /// A value in a data, schema or split file could not be parsed.
+    Parse { line: Option<u64>, message: String },
+    /// Data does not have the shape a schema or fitted component expects.
+    SchemaMismatch(String),
+    /// A record contains a value a model cannot use, such as a missing value.
+    InvalidInput(String),
+    /// `predict` or `transform` was called before `train` or `fit`.
+    NotFitted(&'static str),
+    EmptyDataset,
+    InvalidHyperparameter(String),
+    Plot(String),
+}
+
+pub type Result<T> = std::result::Result<T, PredictorError>;
+
+impl fmt::Display for PredictorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PredictorError::Io(e) => write!(f, "I/O error: {}", e),
+            PredictorError::Parse { line: Some(line), message } => write!(f, "parse error on line {}: {}", line, message),
+            PredictorError::Parse { line: None, message } => write!(f, "parse error: {}", message),
+            PredictorError::SchemaMismatch(message) => write!(f, "schema mismatch: {}", message),
+            PredictorError::InvalidInput(message) => write!(f, "invalid input: {}", message),
+            PredictorError::NotFitted(component) => write!(f, "{} has not been fitted", component),
+            PredictorError::EmptyDataset => write!(f, "dataset is empty"),
+            PredictorError::InvalidHyperparameter(message) => write!(f, "invalid hyperparameter: {}", message),
+            PredictorError::Plot(message) => write!(f, "plotting error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PredictorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PredictorError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PredictorError {
+    fn from(error: std::io::Error) -> Self {
+        PredictorError::Io(error)
+    }
+}
+
+impl From<csv::Error> for PredictorError {
+    fn from(error: csv::Error) -> Self {
+        let line = error.position().map(|position| position.line());
+        let message = error.to_string();
+        match error.into_kind() {
+            csv::ErrorKind::Io(e) => PredictorError::Io(e),
+            _ => PredictorError::Parse { line, message },
+        }
+    }
+}
+
+impl From<serde_json::Error> for PredictorError {
+    fn from(error: serde_json::Error) -> Self {
+        if error.is_io() {
+            return PredictorError::Io(error.into());
+        }
+        PredictorError::Parse {
+            line: Some(error.line() as u64),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl<E: std::error::Error + Send + Sync> From<plotters::drawing::DrawingAreaErrorKind<E>> for PredictorError {
+    fn from(error: plotters::drawing::DrawingAreaErrorKind<E>) -> Self {
+        PredictorError::Plot(error.to_string())
+    }
+}
@@ -1,37 +1,161 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::error::{PredictorError, Result};
 use crate::models::Model;
-use crate::preprocessing::ProcessedPatientRecord;
+use crate::preprocessing::{ProcessedPatientRecord, group_by_site};
+use crate::preprocessing::split::{k_fold, select};
+
+/// Precision, recall and F1 of one class, treating it as the positive class.
+#[derive(Debug, Clone, Default)]
+pub struct ClassMetrics {
+    pub class: u8,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1_score: f32,
+    pub support: u32,
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+pub struct AveragedMetrics {
+    pub precision: f32,
+    pub recall: f32,
+    pub f1_score: f32,
+}
+
+/// `precision`, `recall` and `f1_score` are those of class 1 when the targets
+/// are binary, and the macro averages otherwise.
+#[derive(Debug, Clone, Default)]
 pub struct Metrics {
     pub accuracy: f32,
     pub precision: f32,
     pub recall: f32,
     pub f1_score: f32,
+    pub per_class: Vec<ClassMetrics>,
+    pub macro_avg: AveragedMetrics,
+    pub weighted_avg: AveragedMetrics,
 }
 
-pub fn calculate_metrics(model: &dyn Model, test_data: &[ProcessedPatientRecord]) -> (Metrics, (u32, u32, u32, u32)) {
-    let mut tp = 0;
-    let mut tn = 0;
-    let mut fp = 0;
-    let mut fn_ = 0;
-
-    for record in test_data {
-        let prediction = model.predict(record);
-        match (prediction, record.target) {
-            (1, 1) => tp += 1,
-            (0, 0) => tn += 1,
-            (1, 0) => fp += 1,
-            (0, 1) => fn_ += 1,
-            _ => {},
+/// A K×K confusion matrix. `counts[i][j]` is the number of records of class
+/// `classes[i]` predicted as `classes[j]`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfusionMatrix {
+    pub classes: Vec<u8>,
+    pub counts: Vec<Vec<u32>>,
+}
+
+impl ConfusionMatrix {
+    /// Builds the matrix from (actual, predicted) pairs. When every label is 0
+    /// or 1, both are included so a binary matrix is 2×2 even if one class is
+    /// absent; otherwise only the labels that occur are.
+    pub fn from_pairs(pairs: &[(u8, u8)]) -> Self {
+        let mut classes: Vec<u8> = pairs.iter().flat_map(|&(actual, predicted)| [actual, predicted]).collect();
+        if classes.iter().all(|&class| class <= 1) {
+            classes.extend([0, 1]);
+        }
+        classes.sort_unstable();
+        classes.dedup();
+
+        let mut counts = vec![vec![0; classes.len()]; classes.len()];
+        for &(actual, predicted) in pairs {
+            let row = classes.binary_search(&actual).unwrap();
+            let column = classes.binary_search(&predicted).unwrap();
+            counts[row][column] += 1;
         }
+
+        ConfusionMatrix { classes, counts }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.classes.len() == 2
     }
 
-    let accuracy = (tp + tn) as f32 / test_data.len() as f32;
-    let precision = if (tp + fp) > 0 { tp as f32 / (tp + fp) as f32 } else { 0.0 };
-    let recall = if (tp + fn_) > 0 { tp as f32 / (tp + fn_) as f32 } else { 0.0 };
-    let f1_score = if (precision + recall) > 0.0 { 2.0 * (precision * recall) / (precision + recall) } else { 0.0 };
+    pub fn total(&self) -> u32 {
+        self.counts.iter().flatten().sum()
+    }
+
+    /// (tp, tn, fp, fn) with class 1 as the positive class, for binary matrices.
+    pub fn binary_counts(&self) -> Option<(u32, u32, u32, u32)> {
+        if !self.is_binary() {
+            return None;
+        }
+        Some((self.counts[1][1], self.counts[0][0], self.counts[0][1], self.counts[1][0]))
+    }
+}
 
-    (Metrics { accuracy, precision, recall, f1_score }, (tp, tn, fp, fn_))
+fn ratio(numerator: u32, denominator: u32) -> f32 {
+    if denominator > 0 { numerator as f32 / denominator as f32 } else { 0.0 }
+}
+
+fn f1(precision: f32, recall: f32) -> f32 {
+    if (precision + recall) > 0.0 { 2.0 * (precision * recall) / (precision + recall) } else { 0.0 }
+}
+
+impl Metrics {
+    pub fn from_confusion_matrix(matrix: &ConfusionMatrix) -> Self {
+        let k = matrix.classes.len();
+        let total = matrix.total();
+        let correct: u32 = (0..k).map(|i| matrix.counts[i][i]).sum();
+
+        let per_class: Vec<ClassMetrics> = (0..k)
+            .map(|i| {
+                let tp = matrix.counts[i][i];
+                let support: u32 = matrix.counts[i].iter().sum();
+                let predicted: u32 = matrix.counts.iter().map(|row| row[i]).sum();
+                let precision = ratio(tp, predicted);
+                let recall = ratio(tp, support);
+                ClassMetrics {
+                    class: matrix.classes[i],
+                    precision,
+                    recall,
+                    f1_score: f1(precision, recall),
+                    support,
+                }
+            })
+            .collect();
+
+        let macro_avg = AveragedMetrics {
+            precision: per_class.iter().map(|c| c.precision).sum::<f32>() / k as f32,
+            recall: per_class.iter().map(|c| c.recall).sum::<f32>() / k as f32,
+            f1_score: per_class.iter().map(|c| c.f1_score).sum::<f32>() / k as f32,
+        };
+        let weight = |c: &ClassMetrics| ratio(c.support, total);
+        let weighted_avg = AveragedMetrics {
+            precision: per_class.iter().map(|c| c.precision * weight(c)).sum(),
+            recall: per_class.iter().map(|c| c.recall * weight(c)).sum(),
+            f1_score: per_class.iter().map(|c| c.f1_score * weight(c)).sum(),
+        };
+
+        let (precision, recall, f1_score) = if matrix.is_binary() {
+            (per_class[1].precision, per_class[1].recall, per_class[1].f1_score)
+        } else {
+            (macro_avg.precision, macro_avg.recall, macro_avg.f1_score)
+        };
+
+        Metrics {
+            accuracy: ratio(correct, total),
+            precision,
+            recall,
+            f1_score,
+            per_class,
+            macro_avg,
+            weighted_avg,
+        }
+    }
+}
+
+pub fn calculate_metrics(model: &dyn Model, test_data: &[ProcessedPatientRecord]) -> Result<(Metrics, ConfusionMatrix)> {
+    if test_data.is_empty() {
+        return Err(PredictorError::EmptyDataset);
+    }
+    let pairs: Vec<(u8, u8)> = test_data
+        .iter()
+        .map(|record| record.target)
+        .zip(model.predict_batch(test_data)?)
+        .collect();
+
+    let confusion_matrix = ConfusionMatrix::from_pairs(&pairs);
+    Ok((Metrics::from_confusion_matrix(&confusion_matrix), confusion_matrix))
 }
 
 pub fn print_comparison_table(results: &[(&str, Metrics)]) {
@@ -50,3 +174,233 @@ pub fn print_comparison_table(results: &[(&str, Metrics)]) {
     println!("|---------------------------|----------|-----------|----------|----------|");
 }
 
+/// Per-class precision, recall, F1 and support, followed by the macro and weighted averages.
+pub fn print_classification_report(name: &str, metrics: &Metrics) {
+    println!("{}", name);
+    println!("| {:<12} | Precision | Recall   | F1-Score | Support |", "Class");
+    println!("|--------------|-----------|----------|----------|---------|");
+    for class in &metrics.per_class {
+        println!(
+            "| {:<12} | {:.4}    | {:.4}   | {:.4}   | {:<7} |",
+            class.class, class.precision, class.recall, class.f1_score, class.support
+        );
+    }
+    let support: u32 = metrics.per_class.iter().map(|c| c.support).sum();
+    for (label, average) in [("macro avg", &metrics.macro_avg), ("weighted avg", &metrics.weighted_avg)] {
+        println!(
+            "| {:<12} | {:.4}    | {:.4}   | {:.4}   | {:<7} |",
+            label, average.precision, average.recall, average.f1_score, support
+        );
+    }
+    println!("|--------------|-----------|----------|----------|---------|");
+}
+
+/// Metrics of every fold, with the mean and sample standard deviation of the
+/// accuracy, precision, recall and F1 score.
+#[derive(Debug, Clone, Default)]
+pub struct CrossValidationResult {
+    pub fold_metrics: Vec<Metrics>,
+    pub mean: Metrics,
+    pub std_dev: Metrics,
+}
+
+impl CrossValidationResult {
+    fn from_folds(fold_metrics: Vec<Metrics>) -> Self {
+        let summarise = |metric: fn(&Metrics) -> f32| -> (f32, f32) {
+            let n = fold_metrics.len() as f32;
+            let mean = fold_metrics.iter().map(metric).sum::<f32>() / n;
+            let variance = if fold_metrics.len() > 1 {
+                fold_metrics.iter().map(|m| (metric(m) - mean).powi(2)).sum::<f32>() / (n - 1.0)
+            } else {
+                0.0
+            };
+            (mean, variance.sqrt())
+        };
+
+        let (accuracy, accuracy_sd) = summarise(|m| m.accuracy);
+        let (precision, precision_sd) = summarise(|m| m.precision);
+        let (recall, recall_sd) = summarise(|m| m.recall);
+        let (f1_score, f1_score_sd) = summarise(|m| m.f1_score);
+
+        CrossValidationResult {
+            mean: Metrics { accuracy, precision, recall, f1_score, ..Default::default() },
+            std_dev: Metrics {
+                accuracy: accuracy_sd,
+                precision: precision_sd,
+                recall: recall_sd,
+                f1_score: f1_score_sd,
+                ..Default::default()
+            },
+            fold_metrics,
+        }
+    }
+}
+
+/// Runs k-fold cross-validation. `factory` builds a fresh, untrained model for
+/// each fold, so wrap preprocessing in a `Pipeline` to keep it inside the fold.
+pub fn cross_validate<F>(
+    factory: F,
+    data: &[ProcessedPatientRecord],
+    k: usize,
+    stratified: bool,
+    seed: u64,
+) -> Result<CrossValidationResult>
+where
+    F: Fn() -> Box<dyn Model>,
+{
+    repeated_cross_validate(factory, data, k, 1, stratified, seed)
+}
+
+/// Runs k-fold cross-validation `repeats` times with a different shuffle each
+/// time. The result holds all `k * repeats` folds.
+pub fn repeated_cross_validate<F>(
+    factory: F,
+    data: &[ProcessedPatientRecord],
+    k: usize,
+    repeats: usize,
+    stratified: bool,
+    seed: u64,
+) -> Result<CrossValidationResult>
+where
+    F: Fn() -> Box<dyn Model>,
+{
+    if k < 2 {
+        return Err(PredictorError::InvalidHyperparameter(format!("cross-validation needs at least 2 folds, got {}", k)));
+    }
+    if repeats == 0 {
+        return Err(PredictorError::InvalidHyperparameter("cross-validation needs at least 1 repeat".to_string()));
+    }
+    if data.len() < k {
+        return Err(PredictorError::InvalidHyperparameter(format!(
+            "cannot split {} records into {} folds",
+            data.len(),
+            k
+        )));
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut fold_metrics = Vec::with_capacity(k * repeats);
+    for _ in 0..repeats {
+        for split in k_fold(data, k, stratified, &mut rng)? {
+            let mut model = factory();
+            model.train(&select(data, &split.train))?;
+            let (metrics, _) = calculate_metrics(model.as_ref(), &select(data, &split.test))?;
+            fold_metrics.push(metrics);
+        }
+    }
+
+    Ok(CrossValidationResult::from_folds(fold_metrics))
+}
+
+pub fn print_cross_validation_table(results: &[(&str, CrossValidationResult)]) {
+    println!("| {:<25} | Accuracy        | Precision       | Recall          | F1-Score        |", "Model (mean ± std)");
+    println!("|---------------------------|-----------------|-----------------|-----------------|-----------------|");
+    for (name, result) in results {
+        println!(
+            "| {:<25} | {:.4} ± {:.4} | {:.4} ± {:.4} | {:.4} ± {:.4} | {:.4} ± {:.4} |",
+            name,
+            result.mean.accuracy,
+            result.std_dev.accuracy,
+            result.mean.precision,
+            result.std_dev.precision,
+            result.mean.recall,
+            result.std_dev.recall,
+            result.mean.f1_score,
+            result.std_dev.f1_score
+        );
+    }
+    println!("|---------------------------|-----------------|-----------------|-----------------|-----------------|");
+}
+
+/// Metrics of a model trained on every other site and tested on `site`.
+#[derive(Debug, Clone)]
+pub struct SiteResult {
+    pub site: String,
+    pub train_size: usize,
+    pub test_size: usize,
+    pub metrics: Metrics,
+    pub confusion_matrix: ConfusionMatrix,
+}
+
+/// External validation across hospitals: for each site in turn, trains a fresh
+/// model on all other sites and tests it on the left-out one. Records without
+/// a site are treated as the site "unknown".
+pub fn leave_one_site_out<F>(factory: F, data: &[ProcessedPatientRecord]) -> Result<Vec<SiteResult>>
+where
+    F: Fn() -> Box<dyn Model>,
+{
+    let sites = group_by_site(data);
+    if sites.len() < 2 {
+        return Err(PredictorError::InvalidInput(format!(
+            "leave-one-site-out validation needs at least 2 sites, found {}",
+            sites.len()
+        )));
+    }
+
+    let mut results = Vec::with_capacity(sites.len());
+    for (site, test_set) in &sites {
+        let train_set: Vec<ProcessedPatientRecord> = sites
+            .iter()
+            .filter(|(other, _)| *other != site)
+            .flat_map(|(_, records)| records.iter().cloned())
+            .collect();
+
+        let mut model = factory();
+        model.train(&train_set)?;
+        let (metrics, confusion_matrix) = calculate_metrics(model.as_ref(), test_set)?;
+        results.push(SiteResult {
+            site: site.clone(),
+            train_size: train_set.len(),
+            test_size: test_set.len(),
+            metrics,
+            confusion_matrix,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Prints one comparison table per left-out site, given each model's
+/// leave-one-site-out results.
+pub fn print_site_tables(results: &[(&str, Vec<SiteResult>)]) {
+    // Every site any model reported, in the order first seen; a model that
+    // could not be validated on a site is left out of that site's table
+    let mut sites: Vec<&SiteResult> = Vec::new();
+    for site in results.iter().flat_map(|(_, sites)| sites) {
+        if !sites.iter().any(|seen| seen.site == site.site) {
+            sites.push(site);
+        }
+    }
+    for site in sites {
+        println!(
+            "Held-out site: {} ({} test records, {} training records)",
+            site.site, site.test_size, site.train_size
+        );
+        let table: Vec<(&str, Metrics)> = results
+            .iter()
+            .filter_map(|(name, model_sites)| {
+                let result = model_sites.iter().find(|result| result.site == site.site)?;
+                Some((*name, result.metrics.clone()))
+            })
+            .collect();
+        print_comparison_table(&table);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confusion_matrix_pads_only_binary_labels() {
+        let binary = ConfusionMatrix::from_pairs(&[(1, 1), (1, 1)]);
+        assert_eq!(binary.classes, vec![0, 1]);
+        assert_eq!(binary.counts, vec![vec![0, 0], vec![0, 2]]);
+
+        // A multiclass fold without labels 0 and 1 averages over its own classes
+        let multiclass = ConfusionMatrix::from_pairs(&[(2, 2), (3, 3), (4, 2)]);
+        assert_eq!(multiclass.classes, vec![2, 3, 4]);
+        let metrics = Metrics::from_confusion_matrix(&multiclass);
+        assert_eq!(metrics.per_class.len(), 3);
+    }
+}
@@ -0,0 +1,81 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+pub const DEFAULT_SEED: u64 = 42;
+
+/// Settings for one run. Every random choice in the run draws from a stream
+/// derived from `seed`, so two runs with the same seed, data and settings give
+/// identical results.
+#[derive(Debug, Clone)]
+pub struct ExperimentConfig {
+    pub seed: u64,
+    /// Predict the 0–4 severity of `num` instead of presence of disease.
+    pub multiclass: bool,
+    /// A new batch to compare with the training set for data drift.
+    pub drift_path: Option<String>,
+    /// Run the (slow) sequential feature selection.
+    pub select_features: bool,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        ExperimentConfig {
+            seed: DEFAULT_SEED,
+            multiclass: false,
+            drift_path: None,
+            select_features: false,
+        }
+    }
+}
+
+impl ExperimentConfig {
+    pub fn new(seed: u64) -> Self {
+        ExperimentConfig {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Reads `--seed <N>`, `--multiclass`, `--drift <path>` and
+    /// `--select-features` from command-line arguments (excluding the program name).
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut config = ExperimentConfig::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or("--seed requires a value")?;
+                    config.seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{}'", value))?;
+                }
+                "--multiclass" => config.multiclass = true,
+                "--select-features" => config.select_features = true,
+                "--drift" => config.drift_path = Some(args.next().ok_or("--drift requires a path")?),
+                other => return Err(format!("unknown argument '{}'", other)),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Seed for one named component, e.g. "split" or "cross_validation".
+    /// Each component gets its own stream, so adding randomness to one does
+    /// not shift the numbers drawn by another.
+    pub fn seed_for(&self, component: &str) -> u64 {
+        // FNV-1a over the name, then a splitmix64 finaliser over the combination
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in component.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+
+        let mut z = self.seed ^ hash;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn rng_for(&self, component: &str) -> StdRng {
+        StdRng::seed_from_u64(self.seed_for(component))
+    }
+}
@@ -0,0 +1,515 @@
+use std::collections::BTreeMap;
+
+use crate::error::{PredictorError, Result};
+use crate::evaluation::{Metrics, cross_validate};
+use crate::models::Model;
+use crate::preprocessing::scaling::quantile;
+use crate::preprocessing::{ProcessedPatientRecord, Transformer, check_width};
+
+/// Univariate relevance score of a feature to the target. Higher is more
+/// relevant for every score. Missing values are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreFunction {
+    /// Mutual information in nats. Features with more than 10 distinct values
+    /// are first binned at their deciles, so it suits both continuous
+    /// features and categorical codes.
+    MutualInformation,
+    /// Chi-square statistic of the code × class contingency table, for
+    /// categorical codes. Features with more than 10 distinct values are
+    /// binned at their deciles first, as for mutual information.
+    ChiSquare,
+    /// One-way ANOVA F statistic of the feature across classes, for
+    /// continuous features.
+    AnovaF,
+}
+
+const MI_BINS: usize = 10;
+
+// Non-missing values of one feature paired with the record's class.
+fn feature_column(data: &[ProcessedPatientRecord], i: usize) -> Vec<(f32, u8)> {
+    data.iter()
+        .map(|record| (record.features[i], record.target))
+        .filter(|(value, _)| !value.is_nan())
+        .collect()
+}
+
+// Joint counts of (level, class), with the levels and classes in order.
+fn contingency_table(pairs: &[(usize, u8)]) -> Vec<Vec<f32>> {
+    let mut level_index: BTreeMap<usize, usize> = pairs.iter().map(|&(level, _)| (level, 0)).collect();
+    let mut class_index: BTreeMap<u8, usize> = pairs.iter().map(|&(_, class)| (class, 0)).collect();
+    for (i, index) in level_index.values_mut().enumerate() {
+        *index = i;
+    }
+    for (i, index) in class_index.values_mut().enumerate() {
+        *index = i;
+    }
+
+    let mut table = vec![vec![0.0; class_index.len()]; level_index.len()];
+    for (level, class) in pairs {
+        table[level_index[level]][class_index[class]] += 1.0;
+    }
+    table
+}
+
+// Maps each value to the index of its distinct value, or of its decile bin
+// when there are more than `MI_BINS` distinct values.
+fn discretise(column: &[(f32, u8)]) -> Vec<(usize, u8)> {
+    let mut sorted: Vec<f32> = column.iter().map(|&(value, _)| value).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mut distinct = sorted.clone();
+    distinct.dedup();
+
+    let edges: Vec<f32> = if distinct.len() > MI_BINS {
+        let mut edges: Vec<f32> = (1..MI_BINS).map(|d| quantile(&sorted, d as f32 / MI_BINS as f32)).collect();
+        edges.dedup();
+        edges
+    } else {
+        distinct.windows(2).map(|pair| (pair[0] + pair[1]) / 2.0).collect()
+    };
+    column
+        .iter()
+        .map(|&(value, class)| (edges.partition_point(|&edge| edge < value), class))
+        .collect()
+}
+
+fn mutual_information(column: &[(f32, u8)]) -> f32 {
+    let table = contingency_table(&discretise(column));
+    let n = column.len() as f32;
+    let level_totals: Vec<f32> = table.iter().map(|row| row.iter().sum()).collect();
+    let class_totals: Vec<f32> = (0..table[0].len()).map(|c| table.iter().map(|row| row[c]).sum()).collect();
+
+    let mut information = 0.0;
+    for (row, level_total) in table.iter().zip(&level_totals) {
+        for (&count, class_total) in row.iter().zip(&class_totals) {
+            if count > 0.0 {
+                information += count / n * (count * n / (level_total * class_total)).ln();
+            }
+        }
+    }
+    information
+}
+
+// Continuous features are binned like for mutual information, so a feature
+// with hundreds of distinct values does not get hundreds of levels whose
+// statistic swamps that of a few-level code.
+fn chi_square(column: &[(f32, u8)]) -> f32 {
+    let table = contingency_table(&discretise(column));
+    let n = column.len() as f32;
+    let level_totals: Vec<f32> = table.iter().map(|row| row.iter().sum()).collect();
+    let class_totals: Vec<f32> = (0..table[0].len()).map(|c| table.iter().map(|row| row[c]).sum()).collect();
+
+    let mut statistic = 0.0;
+    for (row, level_total) in table.iter().zip(&level_totals) {
+        for (&observed, class_total) in row.iter().zip(&class_totals) {
+            let expected = level_total * class_total / n;
+            statistic += (observed - expected).powi(2) / expected;
+        }
+    }
+    statistic
+}
+
+fn anova_f(column: &[(f32, u8)]) -> f32 {
+    let mut groups: BTreeMap<u8, Vec<f32>> = BTreeMap::new();
+    for &(value, class) in column {
+        groups.entry(class).or_default().push(value);
+    }
+    let (n, k) = (column.len() as f32, groups.len() as f32);
+    if groups.len() < 2 || column.len() <= groups.len() {
+        return 0.0;
+    }
+
+    let grand_mean = column.iter().map(|&(value, _)| value).sum::<f32>() / n;
+    let mut between = 0.0;
+    let mut within = 0.0;
+    for values in groups.values() {
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        between += values.len() as f32 * (mean - grand_mean).powi(2);
+        within += values.iter().map(|v| (v - mean).powi(2)).sum::<f32>();
+    }
+
+    let between = between / (k - 1.0);
+    let within = within / (n - k);
+    if within > f32::EPSILON {
+        between / within
+    } else if between > f32::EPSILON {
+        f32::INFINITY
+    } else {
+        0.0
+    }
+}
+
+/// Scores every feature of a training set against its target.
+pub fn score_features(data: &[ProcessedPatientRecord], score_function: ScoreFunction) -> Result<Vec<f32>> {
+    let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+    for record in data {
+        check_width(record, num_features)?;
+    }
+
+    Ok((0..num_features)
+        .map(|i| {
+            let column = feature_column(data, i);
+            if column.is_empty() {
+                return 0.0;
+            }
+            match score_function {
+                ScoreFunction::MutualInformation => mutual_information(&column),
+                ScoreFunction::ChiSquare => chi_square(&column),
+                ScoreFunction::AnovaF => anova_f(&column),
+            }
+        })
+        .collect())
+}
+
+// Feature scores and the columns kept, shared by the selectors. `selected` is
+// in the original column order.
+#[derive(Debug, Clone, Default)]
+struct SelectedColumns {
+    scores: Vec<f32>,
+    selected: Vec<usize>,
+}
+
+impl SelectedColumns {
+    fn fit(&mut self, data: &[ProcessedPatientRecord], score_function: ScoreFunction, count: usize) -> Result<()> {
+        self.scores = score_features(data, score_function)?;
+
+        // Highest scores first, ties to the earlier column
+        let mut ranked: Vec<usize> = (0..self.scores.len()).collect();
+        ranked.sort_by(|&a, &b| self.scores[b].total_cmp(&self.scores[a]).then(a.cmp(&b)));
+        ranked.truncate(count);
+        ranked.sort_unstable();
+        self.selected = ranked;
+        Ok(())
+    }
+
+    fn apply(&self, record: &ProcessedPatientRecord, component: &'static str) -> Result<ProcessedPatientRecord> {
+        if self.scores.is_empty() {
+            return Err(PredictorError::NotFitted(component));
+        }
+        check_width(record, self.scores.len())?;
+        Ok(ProcessedPatientRecord {
+            features: self.selected.iter().map(|&i| record.features[i]).collect(),
+            target: record.target,
+            site: record.site.clone(),
+        })
+    }
+
+    // Names pass through unchanged until the selector has been fitted.
+    fn names(&self, input: &[String]) -> Vec<String> {
+        if self.scores.is_empty() {
+            return input.to_vec();
+        }
+        self.selected.iter().filter_map(|&i| input.get(i).cloned()).collect()
+    }
+}
+
+/// Keeps the `k` features with the highest scores on the training split.
+#[derive(Debug, Clone)]
+pub struct SelectKBest {
+    k: usize,
+    score_function: ScoreFunction,
+    columns: SelectedColumns,
+}
+
+impl SelectKBest {
+    pub fn new(k: usize, score_function: ScoreFunction) -> Self {
+        SelectKBest {
+            k,
+            score_function,
+            columns: SelectedColumns::default(),
+        }
+    }
+
+    /// Scores of every input feature, learned by `fit`.
+    pub fn scores(&self) -> &[f32] {
+        &self.columns.scores
+    }
+
+    /// Indices of the kept input features, in their original order.
+    pub fn selected(&self) -> &[usize] {
+        &self.columns.selected
+    }
+}
+
+impl Transformer for SelectKBest {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        if self.k == 0 {
+            return Err(PredictorError::InvalidHyperparameter("k must be at least 1".to_string()));
+        }
+        self.columns.fit(data, self.score_function, self.k)
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.columns.apply(record, "SelectKBest")
+    }
+
+    fn feature_names(&self, input: &[String]) -> Vec<String> {
+        self.columns.names(input)
+    }
+}
+
+/// Keeps the given percentage of features with the highest scores, rounded up
+/// to at least one feature.
+#[derive(Debug, Clone)]
+pub struct SelectPercentile {
+    percentile: f32,
+    score_function: ScoreFunction,
+    columns: SelectedColumns,
+}
+
+impl SelectPercentile {
+    pub fn new(percentile: f32, score_function: ScoreFunction) -> Self {
+        SelectPercentile {
+            percentile,
+            score_function,
+            columns: SelectedColumns::default(),
+        }
+    }
+
+    pub fn scores(&self) -> &[f32] {
+        &self.columns.scores
+    }
+
+    pub fn selected(&self) -> &[usize] {
+        &self.columns.selected
+    }
+}
+
+impl Transformer for SelectPercentile {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        if !(self.percentile > 0.0 && self.percentile <= 100.0) {
+            return Err(PredictorError::InvalidHyperparameter(format!(
+                "percentile must be in (0, 100], got {}",
+                self.percentile
+            )));
+        }
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        let count = ((num_features as f32 * self.percentile / 100.0).ceil() as usize).max(1);
+        self.columns.fit(data, self.score_function, count)
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.columns.apply(record, "SelectPercentile")
+    }
+
+    fn feature_names(&self, input: &[String]) -> Vec<String> {
+        self.columns.names(input)
+    }
+}
+
+/// Prints each feature's scores, ranked by the first score function given.
+pub fn print_feature_scores(feature_names: &[String], scores: &[(ScoreFunction, Vec<f32>)]) {
+    let Some((_, ranking)) = scores.first() else {
+        return;
+    };
+    let mut order: Vec<usize> = (0..feature_names.len()).collect();
+    order.sort_by(|&a, &b| ranking[b].total_cmp(&ranking[a]).then(a.cmp(&b)));
+
+    let header: Vec<String> = scores.iter().map(|(function, _)| format!("{:<18}", format!("{:?}", function))).collect();
+    let separator = format!("|------------|{}", "--------------------|".repeat(scores.len()));
+    println!("| {:<10} | {} |", "Feature", header.join(" | "));
+    println!("{}", separator);
+    for i in order {
+        // A NaN score marks a function that does not apply to the feature
+        let row: Vec<String> = scores
+            .iter()
+            .map(|(_, values)| {
+                if values[i].is_nan() {
+                    format!("{:<18}", "-")
+                } else {
+                    format!("{:<18.4}", values[i])
+                }
+            })
+            .collect();
+        println!("| {:<10} | {} |", feature_names[i], row.join(" | "));
+    }
+    println!("{}", separator);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionDirection {
+    /// Start with no features and add the most useful one at each step.
+    Forward,
+    /// Start with every feature and drop the least useful one at each step.
+    Backward,
+}
+
+/// One feature subset visited by a sequential search, with the mean and
+/// standard deviation of its cross-validated score.
+#[derive(Debug, Clone)]
+pub struct SelectionStep {
+    pub features: Vec<usize>,
+    pub mean: f32,
+    pub std_dev: f32,
+}
+
+/// The subset a sequential search ended on and the score curve leading to it.
+#[derive(Debug, Clone)]
+pub struct SequentialSelection {
+    pub selected: Vec<usize>,
+    pub steps: Vec<SelectionStep>,
+}
+
+/// Wrapper-based feature selection. At each step it cross-validates a fresh
+/// model on every candidate subset, one feature larger or smaller than the
+/// current one, and keeps the best until `target_count` features remain.
+/// Every candidate is scored on the same stratified folds.
+#[derive(Debug, Clone)]
+pub struct SequentialSelector {
+    direction: SelectionDirection,
+    target_count: usize,
+    folds: usize,
+    seed: u64,
+    metric: fn(&Metrics) -> f32,
+}
+
+impl SequentialSelector {
+    /// Selects by 5-fold cross-validated accuracy, on folds drawn from `seed`.
+    pub fn new(direction: SelectionDirection, target_count: usize, seed: u64) -> Self {
+        SequentialSelector {
+            direction,
+            target_count,
+            folds: 5,
+            seed,
+            metric: |metrics| metrics.accuracy,
+        }
+    }
+
+    pub fn with_folds(mut self, folds: usize) -> Self {
+        self.folds = folds;
+        self
+    }
+
+    /// The score to maximise, e.g. `|metrics| metrics.f1_score`.
+    pub fn with_metric(mut self, metric: fn(&Metrics) -> f32) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Runs the search. `factory` builds an untrained model for records that
+    /// hold only the given original feature indices, in that order, so it can
+    /// adapt column-specific preprocessing to the subset.
+    pub fn select<F>(&self, factory: F, data: &[ProcessedPatientRecord]) -> Result<SequentialSelection>
+    where
+        F: Fn(&[usize]) -> Box<dyn Model>,
+    {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        if self.target_count == 0 || self.target_count > num_features {
+            return Err(PredictorError::InvalidHyperparameter(format!(
+                "target feature count must be between 1 and {}, got {}",
+                num_features, self.target_count
+            )));
+        }
+
+        let mut steps = Vec::new();
+        let mut current: Vec<usize> = match self.direction {
+            SelectionDirection::Forward => Vec::new(),
+            SelectionDirection::Backward => {
+                let all: Vec<usize> = (0..num_features).collect();
+                steps.push(self.evaluate(&factory, data, &all)?);
+                all
+            }
+        };
+
+        while current.len() != self.target_count {
+            let candidates: Vec<Vec<usize>> = match self.direction {
+                SelectionDirection::Forward => (0..num_features)
+                    .filter(|i| !current.contains(i))
+                    .map(|i| {
+                        let mut subset = current.clone();
+                        subset.push(i);
+                        subset.sort_unstable();
+                        subset
+                    })
+                    .collect(),
+                SelectionDirection::Backward => (0..current.len())
+                    .map(|position| {
+                        let mut subset = current.clone();
+                        subset.remove(position);
+                        subset
+                    })
+                    .collect(),
+            };
+
+            // The first candidate wins ties
+            let mut best: Option<SelectionStep> = None;
+            for subset in &candidates {
+                let step = self.evaluate(&factory, data, subset)?;
+                if best.as_ref().is_none_or(|best| step.mean > best.mean) {
+                    best = Some(step);
+                }
+            }
+            let best = best.expect("a step always has at least one candidate");
+            current = best.features.clone();
+            steps.push(best);
+        }
+
+        Ok(SequentialSelection { selected: current, steps })
+    }
+
+    fn evaluate<F>(&self, factory: &F, data: &[ProcessedPatientRecord], features: &[usize]) -> Result<SelectionStep>
+    where
+        F: Fn(&[usize]) -> Box<dyn Model>,
+    {
+        let subset = select_features(data, features);
+        let result = cross_validate(|| factory(features), &subset, self.folds, true, self.seed)?;
+        Ok(SelectionStep {
+            features: features.to_vec(),
+            mean: (self.metric)(&result.mean),
+            std_dev: (self.metric)(&result.std_dev),
+        })
+    }
+}
+
+/// Copies the records keeping only the features at `features`, in that order.
+pub fn select_features(data: &[ProcessedPatientRecord], features: &[usize]) -> Vec<ProcessedPatientRecord> {
+    data.iter()
+        .map(|record| ProcessedPatientRecord {
+            features: features.iter().map(|&i| record.features[i]).collect(),
+            target: record.target,
+            site: record.site.clone(),
+        })
+        .collect()
+}
+
+/// Prints the score at each step of a sequential search and the subset it ended on.
+pub fn print_selection_curve(feature_names: &[String], selection: &SequentialSelection) {
+    let names = |features: &[usize]| -> String {
+        features.iter().map(|&i| feature_names[i].as_str()).collect::<Vec<_>>().join(", ")
+    };
+    println!("| Features | Score (mean ± std) | Subset");
+    println!("|----------|--------------------|-------");
+    for step in &selection.steps {
+        println!(
+            "| {:<8} | {:.4} ± {:.4}    | {}",
+            step.features.len(),
+            step.mean,
+            step.std_dev,
+            names(&step.features)
+        );
+    }
+    println!("Selected: {}", names(&selection.selected));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experiment::DEFAULT_SEED;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn chi_square_prefers_an_informative_code_over_continuous_noise() {
+        // Feature 0 is continuous noise, feature 1 is the class itself
+        let mut rng = StdRng::seed_from_u64(DEFAULT_SEED);
+        let data: Vec<ProcessedPatientRecord> = (0..100)
+            .map(|i| ProcessedPatientRecord {
+                features: vec![rng.gen_range(0.0..1.0), (i % 2) as f32],
+                target: (i % 2) as u8,
+                site: None,
+            })
+            .collect();
+        let mut selector = SelectKBest::new(1, ScoreFunction::ChiSquare);
+        selector.fit(&data).unwrap();
+        assert_eq!(selector.selected(), &[1]);
+    }
+}
@@ -1,5 +1,11 @@
+pub mod error;
 pub mod preprocessing;
 pub mod models;
 pub mod ensemble;
+pub mod pipeline;
 pub mod evaluation;
-pub mod visualization;
\ No newline at end of file
+pub mod visualization;
+pub mod experiment;
+pub mod profiling;
+pub mod drift;
+pub mod feature_selection;
\ No newline at end of file
@@ -1,63 +1,549 @@
-mod preprocessing;
-mod models;
-mod ensemble;
-mod evaluation;
-mod visualization;
+use std::path::Path;
 
-use models::{
+use rust_heart_disease_predictor::{preprocessing, profiling, visualization};
+use rust_heart_disease_predictor::feature_selection::{
+    ScoreFunction,
+    SelectionDirection,
+    SequentialSelector,
+    print_feature_scores,
+    print_selection_curve,
+    score_features,
+    select_features,
+};
+use rust_heart_disease_predictor::drift::{DriftReference, DriftThresholds, print_drift_report};
+use rust_heart_disease_predictor::preprocessing::{ProcessedPatientRecord, Transformer};
+use rust_heart_disease_predictor::preprocessing::pca::{Components, PCA};
+use rust_heart_disease_predictor::preprocessing::scaling::StandardScaler;
+use rust_heart_disease_predictor::preprocessing::schema::DatasetSchema;
+use rust_heart_disease_predictor::preprocessing::split::{select, stratified_split};
+use rust_heart_disease_predictor::preprocessing::encoding::{CategoricalEncoder, Encoding};
+use rust_heart_disease_predictor::preprocessing::imputer::{ImputationReport, ImputationStrategy, Imputer};
+use rust_heart_disease_predictor::preprocessing::outliers::{OutlierAction, OutlierDetector, OutlierMethod, OutlierReport};
+use rust_heart_disease_predictor::preprocessing::schema::FeatureKind;
+use rust_heart_disease_predictor::preprocessing::validation::{ValidationAction, ValidationReport, Validator};
+use rust_heart_disease_predictor::models::{
     Model,
-    logistic_regression::LogisticRegression,
+    probability_of,
+    logistic_regression::{CoefficientSummary, LogisticRegression, Penalty, RegularizationPathPoint, Solver},
+    optimization::{Batching, EarlyStopping, LearningRateSchedule, Optimizer},
     naive_bayes::GaussianNB,
     knn::KNN,
     decision_tree::DecisionTree,
 };
-use ensemble::VotingClassifier;
-use evaluation::{calculate_metrics, print_comparison_table};
-use visualization::save_performance_chart;
+use rust_heart_disease_predictor::ensemble::VotingClassifier;
+use rust_heart_disease_predictor::error::PredictorError;
+use rust_heart_disease_predictor::experiment::ExperimentConfig;
+use rust_heart_disease_predictor::pipeline::Pipeline;
+use rust_heart_disease_predictor::evaluation::{
+    CrossValidationResult,
+    calculate_metrics,
+    cross_validate,
+    leave_one_site_out,
+    print_classification_report,
+    print_comparison_table,
+    print_cross_validation_table,
+    print_site_tables,
+};
+use rust_heart_disease_predictor::visualization::save_performance_chart;
+
+const CV_FOLDS: usize = 5;
+
+fn print_imputation_report(split: &str, report: &ImputationReport, feature_names: &[String]) {
+    println!(
+        "Imputed {} missing values in the {} set ({} rows dropped)",
+        report.total_imputed(),
+        split,
+        report.dropped_rows
+    );
+    for (name, &count) in feature_names.iter().zip(report.imputed_per_feature.iter()) {
+        if count > 0 {
+            println!("  {:<10} {}", name, count);
+        }
+    }
+}
+
+fn print_outlier_report(report: &OutlierReport, feature_names: &[String]) {
+    println!("Flagged {} training records as IQR outliers: {:?}", report.outliers.len(), report.outliers);
+    for (name, &count) in feature_names.iter().zip(report.outliers_per_feature.iter()) {
+        if count > 0 {
+            println!("  {:<10} {}", name, count);
+        }
+    }
+}
+
+fn print_validation_report(report: &ValidationReport) {
+    println!(
+        "Validated {} records: {} broke a clinical range rule ({} rejected)",
+        report.records_checked,
+        report.records.len(),
+        report.rejected_rows()
+    );
+    for record in &report.records {
+        let violations: Vec<String> = record.violations.iter().map(|v| v.to_string()).collect();
+        println!(
+            "  record {} ({}): {}",
+            record.index,
+            record.site.as_deref().unwrap_or("unknown"),
+            violations.join("; ")
+        );
+    }
+}
+
+// Positions of the feature columns the preprocessing steps treat differently.
+#[derive(Debug, Clone)]
+struct ColumnRoles {
+    nominal: Vec<usize>,
+    continuous: Vec<usize>,
+}
+
+impl ColumnRoles {
+    // The same roles within records holding only some of the original features.
+    fn subset(&self, features: &[usize]) -> ColumnRoles {
+        let positions = |columns: &[usize]| -> Vec<usize> {
+            features
+                .iter()
+                .enumerate()
+                .filter(|(_, feature)| columns.contains(feature))
+                .map(|(position, _)| position)
+                .collect()
+        };
+        ColumnRoles {
+            nominal: positions(&self.nominal),
+            continuous: positions(&self.continuous),
+        }
+    }
+}
+
+// Continuous measurements take the median; every coded column takes its most
+// frequent code, since a median of codes need not be a code.
+fn imputer(roles: &ColumnRoles) -> Imputer {
+    Imputer::new(ImputationStrategy::MostFrequent).with_column_strategy(&roles.continuous, ImputationStrategy::Median)
+}
+
+// Imputes missing values, standardises the continuous measurements and
+// one-hot encodes the nominal codes so they are not treated as magnitudes.
+// Scaling comes first so it can name the columns by their original positions;
+// the codes and indicators keep their 0/1 meaning. Every step is fitted on the
+// training split inside the pipeline.
+fn preprocessing_steps(roles: &ColumnRoles) -> Vec<Box<dyn Transformer>> {
+    vec![
+        Box::new(imputer(roles)),
+        Box::new(StandardScaler::new().with_columns(roles.continuous.clone())),
+        Box::new(CategoricalEncoder::new(roles.nominal.clone(), Encoding::OneHot)),
+    ]
+}
+
+fn with_preprocessing(roles: &ColumnRoles, model: Box<dyn Model>) -> Box<dyn Model> {
+    Box::new(Pipeline::new(preprocessing_steps(roles), model))
+}
+
+// Non-zero weights and the three largest weights at each strength of the path.
+fn print_regularization_path(path: &[RegularizationPathPoint], feature_names: &[String]) {
+    println!("| Strength | Non-zero | Largest weights |");
+    println!("|---|---|---|");
+    for point in path {
+        // Binary targets have a single weight vector; with more classes show the first
+        let coefficients = &point.coefficients[0];
+        let mut order: Vec<usize> = (0..coefficients.len()).collect();
+        order.sort_by(|&a, &b| coefficients[b].abs().total_cmp(&coefficients[a].abs()));
+        let largest: Vec<String> = order
+            .iter()
+            .take(3)
+            .filter(|&&i| coefficients[i] != 0.0)
+            .map(|&i| format!("{} {:+.3}", feature_names[i], coefficients[i]))
+            .collect();
+        println!(
+            "| {} | {} | {} |",
+            point.strength,
+            coefficients.iter().filter(|&&w| w != 0.0).count(),
+            if largest.is_empty() { "-".to_string() } else { largest.join(", ") }
+        );
+    }
+}
+
+type ModelFactory = Box<dyn Fn() -> Box<dyn Model>>;
+
+fn pipeline_factory(roles: &ColumnRoles, build: impl Fn() -> Box<dyn Model> + 'static) -> ModelFactory {
+    let roles = roles.clone();
+    Box::new(move || with_preprocessing(&roles, build()))
+}
+
+// A factory for records holding only some of the original features: the
+// column roles are looked up again within the subset.
+fn subset_factory(roles: &ColumnRoles, build: impl Fn() -> Box<dyn Model>) -> impl Fn(&[usize]) -> Box<dyn Model> {
+    let roles = roles.clone();
+    move |features: &[usize]| with_preprocessing(&roles.subset(features), build())
+}
+
+// Builders for every model under evaluation, so each split or fold gets a fresh model.
+// `seed` is the logistic regression's.
+fn model_factories(roles: &ColumnRoles, seed: u64) -> Vec<(&'static str, ModelFactory)> {
+    vec![
+        ("Logistic Regression", pipeline_factory(roles, move || Box::new(LogisticRegression::new(0.01, 1000, seed)))),
+        ("Gaussian Naive Bayes", pipeline_factory(roles, || Box::new(GaussianNB::new()))),
+        ("KNN", pipeline_factory(roles, || Box::new(KNN::new(5)))),
+        ("Decision Tree", pipeline_factory(roles, || Box::new(DecisionTree::new(10, 2)))),
+        // Ensemble of all four models, sharing one preprocessing pipeline
+        ("Voting Classifier", pipeline_factory(roles, move || {
+            Box::new(VotingClassifier::new(vec![
+                Box::new(LogisticRegression::new(0.01, 1000, seed)),
+                Box::new(GaussianNB::new()),
+                Box::new(KNN::new(5)),
+                Box::new(DecisionTree::new(10, 2)),
+            ]))
+        })),
+    ]
+}
+
+// Estimated probability of any heart disease for a few test patients, per model.
+fn print_risk_table(models: &[(&str, Box<dyn Model>)], records: &[ProcessedPatientRecord]) {
+    println!("Estimated risk of heart disease for the first {} test patients:", records.len());
+    let names: Vec<&str> = models.iter().map(|(name, _)| *name).collect();
+    println!("| Patient | Actual | {} |", names.join(" | "));
+    println!("|---|---|{}", "---|".repeat(names.len()));
+    for (i, record) in records.iter().enumerate() {
+        let risks: Vec<String> = models
+            .iter()
+            .map(|(_, model)| match model.predict_proba(record) {
+                Ok(probabilities) => format!("{:.1}%", (1.0 - probability_of(&probabilities, 0)) * 100.0),
+                Err(_) => "-".to_string(),
+            })
+            .collect();
+        println!("| {} | {} | {} |", i + 1, record.target, risks.join(" | "));
+    }
+    println!();
+}
+
+// Coefficients with their Wald statistics and odds ratios.
+fn print_coefficient_summary(summary: &[CoefficientSummary]) {
+    println!("| Class | Feature | Coefficient | Std. error | z | p-value | 95% CI | Odds ratio | Odds ratio 95% CI |");
+    println!("|---|---|---|---|---|---|---|---|---|");
+    for row in summary {
+        println!(
+            "| {} | {} | {:.4} | {:.4} | {:.2} | {:.4} | [{:.4}, {:.4}] | {:.3} | [{:.3}, {:.3}] |",
+            row.class,
+            row.name,
+            row.coefficient,
+            row.standard_error,
+            row.z,
+            row.p_value,
+            row.lower,
+            row.upper,
+            row.odds_ratio,
+            row.odds_ratio_lower,
+            row.odds_ratio_upper
+        );
+    }
+    println!();
+}
 
 fn main() {
     println!("Rust Heart Disease Predictor");
 
-    // Load and preprocess data
-    let data_path = "data/processed.cleveland.data";
-    let mut records = match preprocessing::load_and_preprocess_data(data_path) {
-        Ok(records) => records,
+    let config = match ExperimentConfig::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("Usage: rust_heart_disease_predictor [--seed <N>] [--multiclass] [--drift <path>] [--select-features]");
+            return;
+        }
+    };
+    println!("Experiment seed: {}", config.seed);
+
+    // Load every UCI site whose data file is present
+    let site_paths: Vec<(&str, String)> = preprocessing::UCI_SITES
+        .iter()
+        .map(|&(site, file)| (site, format!("data/{}", file)))
+        .filter(|(_, path)| Path::new(path).exists())
+        .collect();
+    let sources: Vec<(&str, &str)> = site_paths.iter().map(|(site, path)| (*site, path.as_str())).collect();
+
+    let schema = DatasetSchema::uci_heart().with_binary_target(!config.multiclass);
+    let dataset = match preprocessing::load_sites(&sources, &schema) {
+        Ok(dataset) => dataset,
         Err(e) => {
             eprintln!("Error loading data: {}", e);
             return;
         }
     };
 
-    // Split data
-    let (train_set, test_set) = preprocessing::train_test_split(&mut records, 0.2);
-
-    // Create ensemble with all four models
-    let ensemble = VotingClassifier::new(vec![
-        Box::new(LogisticRegression::new(0.01, 1000)),
-        Box::new(GaussianNB::new()),
-        Box::new(KNN::new(5)),
-        Box::new(DecisionTree::new(10, 2)),
-    ]);
-
-    let mut models: Vec<(&str, Box<dyn Model>)> = vec![
-        ("Logistic Regression", Box::new(LogisticRegression::new(0.01, 1000))),
-        ("Gaussian Naive Bayes", Box::new(GaussianNB::new())),
-        ("KNN", Box::new(KNN::new(5))),
-        ("Decision Tree", Box::new(DecisionTree::new(10, 2))),
-        ("Voting Classifier", Box::new(ensemble)),
-    ];
+    for (site, site_records) in preprocessing::group_by_site(&dataset.records) {
+        println!("Loaded {} records from {}", site_records.len(), site);
+    }
+
+    // Drop records with clinically implausible values before they reach a model
+    let dataset = match Validator::uci_heart(ValidationAction::Reject).validate(&dataset) {
+        Ok((dataset, report)) => {
+            print_validation_report(&report);
+            dataset
+        }
+        Err(e) => {
+            eprintln!("Error validating data: {}", e);
+            return;
+        }
+    };
+
+    let data_profile = profiling::profile(&dataset);
+    profiling::print_profile(&data_profile);
+    if let Err(e) = data_profile.save("data_profile.json") {
+        eprintln!("Error saving data profile: {}", e);
+    }
+
+    // Stratified split, so both parts keep the class ratio
+    let mut rng = config.rng_for("split");
+    let split = stratified_split(&dataset.records, 0.2, &mut rng);
+    let train_set = select(&dataset.records, &split.train);
+    let test_set = select(&dataset.records, &split.test);
+
+    // Keep the training distribution so new batches can be checked against it
+    let reference = match DriftReference::fit(&dataset.with_records(train_set.clone())) {
+        Ok(reference) => reference,
+        Err(e) => {
+            eprintln!("Error fitting drift reference: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = reference.save("drift_reference.json") {
+        eprintln!("Error saving drift reference: {}", e);
+    }
+    if let Some(path) = &config.drift_path {
+        let report = preprocessing::load_dataset(path, &schema)
+            .and_then(|batch| reference.compare(&batch, &DriftThresholds::default()));
+        match report {
+            Ok(report) => {
+                println!("Drift of {} against the training set:", path);
+                print_drift_report(&report);
+            }
+            Err(e) => eprintln!("Error checking drift of {}: {}", path, e),
+        }
+    }
+
+    let roles = ColumnRoles {
+        nominal: ["cp", "restecg", "slope", "thal"]
+            .iter()
+            .filter_map(|name| dataset.feature_index(name))
+            .collect(),
+        continuous: (0..dataset.feature_kinds.len())
+            .filter(|&i| dataset.feature_kinds[i] == FeatureKind::Continuous)
+            .collect(),
+    };
+
+    // Report the gaps the pipelines' imputers will fill
+    let mut imputer = imputer(&roles);
+    let imputed = imputer.fit(&train_set).and_then(|_| {
+        let (imputed_train_set, train_report) = imputer.transform_with_report(&train_set)?;
+        let (_, test_report) = imputer.transform_with_report(&test_set)?;
+        Ok((imputed_train_set, train_report, test_report))
+    });
+    let (imputed_train_set, train_report, test_report) = match imputed {
+        Ok(imputed) => imputed,
+        Err(e) => {
+            eprintln!("Error imputing missing values: {}", e);
+            return;
+        }
+    };
+    print_imputation_report("training", &train_report, &dataset.feature_names);
+    print_imputation_report("test", &test_report, &dataset.feature_names);
+
+    // Report the extreme values of the continuous features in the training split
+    let mut outlier_detector =
+        OutlierDetector::new(OutlierMethod::iqr(), OutlierAction::Flag, config.seed_for("outliers"))
+            .with_columns(roles.continuous.clone());
+    match outlier_detector
+        .fit(&imputed_train_set)
+        .and_then(|_| outlier_detector.transform_with_report(&imputed_train_set))
+    {
+        Ok((_, report)) => print_outlier_report(&report, &dataset.feature_names),
+        Err(e) => eprintln!("Error detecting outliers: {}", e),
+    }
+
+    // Univariate relevance of each attribute to the target
+    let score_functions = [ScoreFunction::MutualInformation, ScoreFunction::ChiSquare, ScoreFunction::AnovaF];
+    let feature_scores: Result<Vec<_>, PredictorError> = score_functions
+        .iter()
+        .map(|&function| {
+            let mut scores = score_features(&imputed_train_set, function)?;
+            // Chi-square is only meaningful for the categorical codes
+            if function == ScoreFunction::ChiSquare {
+                for &i in &roles.continuous {
+                    scores[i] = f32::NAN;
+                }
+            }
+            Ok((function, scores))
+        })
+        .collect();
+    match feature_scores {
+        Ok(feature_scores) => {
+            println!("Feature scores on the training set:");
+            print_feature_scores(&dataset.feature_names, &feature_scores);
+        }
+        Err(e) => eprintln!("Error scoring features: {}", e),
+    }
+
+    // How many directions carry most of the variance of the standardised features
+    let mut pca = PCA::new(Components::VarianceRatio(0.9));
+    match StandardScaler::new()
+        .fit_transform(&imputed_train_set)
+        .and_then(|standardised| pca.fit(&standardised))
+    {
+        Ok(()) => {
+            let ratios: Vec<String> = pca
+                .explained_variance_ratio()
+                .iter()
+                .enumerate()
+                .map(|(i, ratio)| format!("PC{} {:.1}%", i + 1, ratio * 100.0))
+                .collect();
+            println!("PCA: {} components explain 90% of the variance ({})", pca.n_components(), ratios.join(", "));
+        }
+        Err(e) => eprintln!("Error fitting PCA: {}", e),
+    }
+
+    let logistic_seed = config.seed_for("logistic_regression");
+    let factories = model_factories(&roles, logistic_seed);
 
     let mut results = Vec::new();
     let mut confusion_matrices = Vec::new();
+    let mut trained_models = Vec::new();
 
-    for (name, model) in &mut models {
-        model.train(&train_set);
-        let (metrics, confusion_matrix) = calculate_metrics(model.as_ref(), &test_set);
-        results.push((*name, metrics));
-        confusion_matrices.push((*name, confusion_matrix));
+    for (name, factory) in &factories {
+        let mut model = factory();
+        let evaluated = model
+            .train(&train_set)
+            .and_then(|_| calculate_metrics(model.as_ref(), &test_set));
+        match evaluated {
+            Ok((metrics, confusion_matrix)) => {
+                results.push((*name, metrics));
+                confusion_matrices.push((*name, confusion_matrix));
+                trained_models.push((*name, model));
+            }
+            Err(e) => eprintln!("Error evaluating {}: {}", name, e),
+        }
     }
 
     print_comparison_table(&results);
+    print_risk_table(&trained_models, &test_set[..test_set.len().min(5)]);
+
+    if config.multiclass {
+        for (name, metrics) in &results {
+            print_classification_report(name, metrics);
+        }
+    }
+
+    // Stratified 5-fold cross-validation over the whole dataset
+    println!("Cross-validation ({} folds, stratified):", CV_FOLDS);
+    let cv_seed = config.seed_for("cross_validation");
+    let mut cv_results: Vec<(&str, CrossValidationResult)> = Vec::new();
+    for (name, factory) in &factories {
+        match cross_validate(factory.as_ref(), &dataset.records, CV_FOLDS, true, cv_seed) {
+            Ok(result) => cv_results.push((*name, result)),
+            Err(e) => eprintln!("Error cross-validating {}: {}", name, e),
+        }
+    }
+    print_cross_validation_table(&cv_results);
+
+    // How an L1 penalty prunes the logistic regression weights as it grows
+    let mut steps = preprocessing_steps(&roles);
+    let preprocessed = steps
+        .iter_mut()
+        .try_fold(train_set.clone(), |records, step| step.fit_transform(&records));
+    let preprocessed = match preprocessed {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("Error preprocessing the training set: {}", e);
+            return;
+        }
+    };
+    let path = LogisticRegression::new(0.01, 1000, logistic_seed)
+        .with_penalty(Penalty::L1(0.0))
+        .regularization_path(&preprocessed, &[0.001, 0.01, 0.03, 0.1, 0.3]);
+    match path {
+        Ok(path) => {
+            let names = steps
+                .iter()
+                .fold(dataset.feature_names.clone(), |names, step| step.feature_names(&names));
+            println!("L1 regularization path for Logistic Regression:");
+            print_regularization_path(&path, &names);
+        }
+        Err(e) => eprintln!("Error computing the regularization path: {}", e),
+    }
+
+    // Adam on shuffled mini-batches, stopping once a held-out tenth of the
+    // training set stops improving
+    let mut adam = LogisticRegression::new(0.01, 1000, logistic_seed)
+        .with_optimizer(Optimizer::adam())
+        .with_batching(Batching::MiniBatch(32))
+        .with_schedule(LearningRateSchedule::InverseTime { decay: 0.01 })
+        .with_early_stopping(EarlyStopping::validation_plateau())
+        .with_shuffle(true);
+    match adam.train(&preprocessed) {
+        Ok(()) => {
+            let history = &adam.loss_history()[0];
+            // The weights kept are those of the epoch with the best validation loss
+            let best = history
+                .iter()
+                .filter_map(|loss| loss.validation_loss.map(|v| (loss.epoch, v)))
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((epoch, validation_loss)) = best {
+                println!(
+                    "Logistic Regression with Adam stopped after {} epochs; best validation loss {:.4} at epoch {}",
+                    history.len(),
+                    validation_loss,
+                    epoch + 1
+                );
+            }
+            if let Err(e) = visualization::create_loss_curve_chart(history, "loss_curve.png") {
+                eprintln!("Error creating loss curve chart: {}", e);
+            }
+        }
+        Err(e) => eprintln!("Error training Logistic Regression with Adam: {}", e),
+    }
+
+    // A clinical reading of the unscaled non-nominal features, so each odds
+    // ratio is per unit of its feature. One-hot indicators of every category
+    // would be collinear with the intercept.
+    let clinical_columns: Vec<usize> = (0..dataset.feature_names.len())
+        .filter(|i| !roles.nominal.contains(i))
+        .collect();
+    let clinical_names: Vec<String> = clinical_columns.iter().map(|&i| dataset.feature_names[i].clone()).collect();
+    let mut newton = LogisticRegression::new(0.01, 1000, logistic_seed).with_solver(Solver::newton());
+    match newton
+        .train(&select_features(&imputed_train_set, &clinical_columns))
+        .and_then(|_| newton.summary(&clinical_names))
+    {
+        Ok(summary) => {
+            println!(
+                "Logistic Regression fitted by Newton's method in {} iterations:",
+                newton.loss_history()[0].len()
+            );
+            print_coefficient_summary(&summary);
+        }
+        Err(e) => eprintln!("Error fitting Logistic Regression by Newton's method: {}", e),
+    }
+
+    // Drop features from logistic regression one at a time down to six
+    if config.select_features {
+        println!("Backward feature selection for Logistic Regression ({} folds, accuracy):", CV_FOLDS);
+        let selector = SequentialSelector::new(SelectionDirection::Backward, 6, config.seed_for("feature_selection"))
+            .with_folds(CV_FOLDS);
+        let factory = subset_factory(&roles, move || Box::new(LogisticRegression::new(0.01, 1000, logistic_seed)));
+        match selector.select(factory, &dataset.records) {
+            Ok(selection) => print_selection_curve(&dataset.feature_names, &selection),
+            Err(e) => eprintln!("Error selecting features: {}", e),
+        }
+    }
+
+    // Train on every site but one and test on the one left out
+    if preprocessing::group_by_site(&dataset.records).len() > 1 {
+        println!("Leave-one-site-out validation:");
+        let mut site_results = Vec::new();
+        for (name, factory) in &factories {
+            match leave_one_site_out(factory.as_ref(), &dataset.records) {
+                Ok(result) => site_results.push((*name, result)),
+                Err(e) => eprintln!("Error validating {} across sites: {}", name, e),
+            }
+        }
+        print_site_tables(&site_results);
+    }
 
     if let Err(e) = save_performance_chart(&results) {
         eprintln!("Error saving performance chart: {}", e);
@@ -74,18 +560,18 @@ fn main() {
     // Create confusion matrix visualizations
     for (name, confusion_matrix) in &confusion_matrices {
         let filename = format!("confusion_matrix_{}.png", name.replace(" ", "_").to_lowercase());
-        if let Err(e) = visualization::create_confusion_matrix_heatmap(name, *confusion_matrix, &filename) {
+        if let Err(e) = visualization::create_confusion_matrix_heatmap(name, confusion_matrix, &filename) {
             eprintln!("Error creating confusion matrix heatmap for {}: {}", name, e);
         }
     }
 
     // Create feature distribution histogram
-    if let Err(e) = visualization::create_feature_histograms(&train_set, "feature_histogram.png") {
+    if let Err(e) = visualization::create_feature_histograms(&imputed_train_set, "feature_histogram.png") {
         eprintln!("Error creating feature histogram: {}", e);
     }
 
     // Create correlation matrix heatmap
-    if let Err(e) = visualization::create_correlation_matrix_heatmap(&train_set, "correlation_matrix.png") {
+    if let Err(e) = visualization::create_correlation_matrix_heatmap(&imputed_train_set, "correlation_matrix.png") {
         eprintln!("Error creating correlation matrix: {}", e);
     }
 
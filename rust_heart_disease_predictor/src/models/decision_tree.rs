@@ -1,8 +1,11 @@
+use crate::error::{PredictorError, Result};
 use crate::preprocessing::ProcessedPatientRecord;
 
 #[derive(Debug, Clone)]
 pub enum Node {
-    Leaf(u8),
+    /// The majority class of the training records that reached the leaf, and
+    /// their class proportions indexed by class label.
+    Leaf { class: u8, probabilities: Vec<f32> },
     Internal {
         feature_index: usize,
         threshold: f32,
@@ -13,21 +16,39 @@ pub enum Node {
 
 pub struct DecisionTree {
     root: Option<Node>,
+    num_features: usize,
+    num_classes: usize,
     max_depth: usize,
     min_samples_split: usize,
 }
 
 impl super::Model for DecisionTree {
-    fn train(&mut self, training_data: &[ProcessedPatientRecord]) {
-        if !training_data.is_empty() {
-            self.root = Some(self.build_tree(training_data, 0));
+    fn train(&mut self, training_data: &[ProcessedPatientRecord]) -> Result<()> {
+        self.num_features = super::check_training_data(training_data)?;
+        // Every leaf reports the same classes, whichever records reached it
+        self.num_classes = training_data.iter().map(|record| record.target as usize + 1).max().unwrap_or(1);
+        self.root = Some(self.build_tree(training_data, 0));
+        Ok(())
+    }
+
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8> {
+        match &self.root {
+            Some(node) => {
+                super::check_record(record, self.num_features)?;
+                Ok(self.predict_from_node(node, &record.features).0)
+            }
+            None => Err(PredictorError::NotFitted("DecisionTree")),
         }
     }
 
-    fn predict(&self, record: &ProcessedPatientRecord) -> u8 {
+    /// The class proportions of the training records in the record's leaf.
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>> {
         match &self.root {
-            Some(node) => self.predict_from_node(node, &record.features),
-            None => 0, // Default prediction if tree wasn't built
+            Some(node) => {
+                super::check_record(record, self.num_features)?;
+                Ok(self.predict_from_node(node, &record.features).1.to_vec())
+            }
+            None => Err(PredictorError::NotFitted("DecisionTree")),
         }
     }
 }
@@ -36,6 +57,8 @@ impl DecisionTree {
     pub fn new(max_depth: usize, min_samples_split: usize) -> Self {
         DecisionTree {
             root: None,
+            num_features: 0,
+            num_classes: 0,
             max_depth,
             min_samples_split,
         }
@@ -45,18 +68,18 @@ impl DecisionTree {
     fn build_tree(&self, data: &[ProcessedPatientRecord], depth: usize) -> Node {
         // Check stopping conditions
         if data.is_empty() {
-            return Node::Leaf(0);
+            return self.leaf(data, 0);
         }
 
         // Check if all samples have the same target
         let first_target = data[0].target;
         if data.iter().all(|record| record.target == first_target) {
-            return Node::Leaf(first_target);
+            return self.leaf(data, first_target);
         }
 
         // Check stopping conditions: max depth or minimum samples
         if depth >= self.max_depth || data.len() < self.min_samples_split {
-            return Node::Leaf(self.most_common_class(data));
+            return self.leaf(data, self.most_common_class(data));
         }
 
         // Find the best split
@@ -74,10 +97,21 @@ impl DecisionTree {
             }
         } else {
             // If no good split is found, create a leaf with the majority class
-            Node::Leaf(self.most_common_class(data))
+            self.leaf(data, self.most_common_class(data))
         }
     }
 
+    fn leaf(&self, data: &[ProcessedPatientRecord], class: u8) -> Node {
+        let mut probabilities = vec![0.0; self.num_classes.max(class as usize + 1)];
+        if data.is_empty() {
+            probabilities[class as usize] = 1.0;
+        }
+        for record in data {
+            probabilities[record.target as usize] += 1.0 / data.len() as f32;
+        }
+        Node::Leaf { class, probabilities }
+    }
+
     fn find_best_split(&self, data: &[ProcessedPatientRecord]) -> Option<(usize, f32)> {
         if data.is_empty() {
             return None;
@@ -143,10 +177,8 @@ impl DecisionTree {
         let left_gini = self.calculate_gini(left_data);
         let right_gini = self.calculate_gini(right_data);
 
-        let weighted_gini = (left_data.len() as f32 / total_size) * left_gini
-            + (right_data.len() as f32 / total_size) * right_gini;
-
-        weighted_gini
+        (left_data.len() as f32 / total_size) * left_gini
+            + (right_data.len() as f32 / total_size) * right_gini
     }
 
     fn calculate_gini(&self, data: &[ProcessedPatientRecord]) -> f32 {
@@ -154,7 +186,7 @@ impl DecisionTree {
             return 0.0;
         }
 
-        let mut class_counts = std::collections::HashMap::new();
+        let mut class_counts = std::collections::BTreeMap::new();
         for record in data {
             *class_counts.entry(record.target).or_insert(0) += 1;
         }
@@ -171,7 +203,7 @@ impl DecisionTree {
     }
 
     fn most_common_class(&self, data: &[ProcessedPatientRecord]) -> u8 {
-        let mut class_counts = std::collections::HashMap::new();
+        let mut class_counts = std::collections::BTreeMap::new();
         for record in data {
             *class_counts.entry(record.target).or_insert(0) += 1;
         }
@@ -183,9 +215,9 @@ impl DecisionTree {
             .unwrap_or(0)
     }
 
-    fn predict_from_node(&self, node: &Node, features: &[f32]) -> u8 {
+    fn predict_from_node<'a>(&self, node: &'a Node, features: &[f32]) -> (u8, &'a [f32]) {
         match node {
-            Node::Leaf(class) => *class,
+            Node::Leaf { class, probabilities } => (*class, probabilities),
             Node::Internal {
                 feature_index,
                 threshold,
@@ -201,3 +233,27 @@ impl DecisionTree {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::Model;
+    use super::*;
+
+    #[test]
+    fn pure_leaves_report_every_class() {
+        let data: Vec<ProcessedPatientRecord> = [(0.0, 0), (1.0, 0), (2.0, 0), (3.0, 1), (4.0, 1), (4.0, 0)]
+            .iter()
+            .map(|&(value, target)| ProcessedPatientRecord {
+                features: vec![value],
+                target,
+                site: None,
+            })
+            .collect();
+        let mut tree = DecisionTree::new(5, 2);
+        tree.train(&data).unwrap();
+
+        // x = 0 lands in a pure class-0 leaf, x = 4 in a mixed one
+        assert_eq!(tree.predict_proba(&data[0]).unwrap(), vec![1.0, 0.0]);
+        assert_eq!(tree.predict_proba(&data[4]).unwrap(), vec![0.5, 0.5]);
+    }
+}
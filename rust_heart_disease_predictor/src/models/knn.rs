@@ -1,77 +1,146 @@
+use ndarray::{Array2, Axis};
+
+use crate::error::{PredictorError, Result};
 use crate::preprocessing::ProcessedPatientRecord;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
+/// k-nearest neighbours by Euclidean distance. Training stores the records
+/// as a matrix, so the distances from each query record to every training
+/// record are computed in one array operation.
 pub struct KNN {
-    training_data: Vec<ProcessedPatientRecord>,
+    features: Array2<f32>,
+    targets: Vec<u8>,
     k: usize,
 }
 
 impl super::Model for KNN {
-    fn train(&mut self, training_data: &[ProcessedPatientRecord]) {
-        self.training_data = training_data.to_vec();
-    }
-
-    fn predict(&self, record: &ProcessedPatientRecord) -> u8 {
-        if self.training_data.is_empty() {
-            return 0; // Default prediction if no training data
+    fn train(&mut self, training_data: &[ProcessedPatientRecord]) -> Result<()> {
+        if self.k == 0 {
+            return Err(PredictorError::InvalidHyperparameter("k must be at least 1".to_string()));
         }
+        let num_features = super::check_training_data(training_data)?;
+        self.features = Self::matrix(training_data, num_features);
+        self.targets = training_data.iter().map(|record| record.target).collect();
+        Ok(())
+    }
 
-        let mut distances = Vec::new();
-
-        for train_record in &self.training_data {
-            let distance = self.euclidean_distance(&record.features, &train_record.features);
-            distances.push((distance, train_record.target));
-        }
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8> {
+        self.predict_batch(std::slice::from_ref(record)).map(|predictions| predictions[0])
+    }
 
-        // Sort by distance (ascending)
-        distances.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    /// The fraction of the k nearest neighbours in each class.
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>> {
+        self.predict_proba_batch(std::slice::from_ref(record))
+            .map(|mut probabilities| probabilities.remove(0))
+    }
 
-        // Get the k nearest neighbors
-        let k_nearest: Vec<u8> = distances
+    fn predict_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<u8>> {
+        // Return the majority vote
+        Ok(self
+            .nearest_targets(records)?
             .iter()
-            .take(self.k)
-            .map(|(_, target)| *target)
-            .collect();
+            .map(|k_nearest| self.majority_vote(k_nearest))
+            .collect())
+    }
 
-        // Return the majority vote
-        self.majority_vote(&k_nearest)
+    fn predict_proba_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<Vec<f32>>> {
+        let largest_class = self.targets.iter().copied().max().unwrap_or(0);
+        Ok(self
+            .nearest_targets(records)?
+            .iter()
+            .map(|k_nearest| {
+                let mut probabilities = vec![0.0; largest_class as usize + 1];
+                for &target in k_nearest {
+                    probabilities[target as usize] += 1.0 / k_nearest.len() as f32;
+                }
+                probabilities
+            })
+            .collect())
     }
 }
 
 impl KNN {
     pub fn new(k: usize) -> Self {
         KNN {
-            training_data: Vec::new(),
+            features: Array2::zeros((0, 0)),
+            targets: Vec::new(),
             k,
         }
     }
-    
-    fn euclidean_distance(&self, a: &[f32], b: &[f32]) -> f32 {
-        if a.len() != b.len() {
-            return f32::MAX; // Handle mismatched dimensions
-        }
 
-        let sum_of_squares: f32 = a
-            .iter()
-            .zip(b.iter())
-            .map(|(x, y)| (x - y).powi(2))
-            .sum();
+    fn matrix(records: &[ProcessedPatientRecord], num_features: usize) -> Array2<f32> {
+        Array2::from_shape_fn((records.len(), num_features), |(i, j)| records[i].features[j])
+    }
+
+    // Targets of the k nearest training records to each record, nearest
+    // first. Ties go to the earlier training record.
+    fn nearest_targets(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<Vec<u8>>> {
+        if self.targets.is_empty() {
+            return Err(PredictorError::NotFitted("KNN"));
+        }
+        let num_features = self.features.ncols();
+        for record in records {
+            super::check_record(record, num_features)?;
+        }
 
-        sum_of_squares.sqrt()
+        // Squared distances from the exact differences: the expansion
+        // |a|^2 - 2 a.b + |b|^2 cancels catastrophically on unscaled features.
+        // The square root does not change the order, so it is skipped.
+        let batch = Self::matrix(records, num_features);
+        Ok(batch
+            .outer_iter()
+            .map(|query| {
+                let distances = (&self.features - &query).mapv(|d| d * d).sum_axis(Axis(1));
+                let mut order: Vec<usize> = (0..distances.len()).collect();
+                order.sort_by(|&a, &b| distances[a].total_cmp(&distances[b]));
+                order.iter().take(self.k).map(|&i| self.targets[i]).collect()
+            })
+            .collect())
     }
 
+    // Neighbors are in order of increasing distance, so a tie goes to the
+    // class of the nearest tied neighbor.
     fn majority_vote(&self, neighbors: &[u8]) -> u8 {
-        let mut vote_counts = HashMap::new();
+        let mut vote_counts = BTreeMap::new();
 
         for &vote in neighbors {
             *vote_counts.entry(vote).or_insert(0) += 1;
         }
 
         // Find the vote with the maximum count
-        vote_counts
-            .into_iter()
-            .max_by_key(|&(_, count)| count)
-            .map(|(val, _)| val)
-            .unwrap_or(0) // Default to 0 if no votes (shouldn't happen with valid k)
+        let mut best_vote = 0; // Default to 0 if no votes (shouldn't happen with valid k)
+        let mut best_count = 0;
+        for vote in neighbors {
+            let count = vote_counts[vote];
+            if count > best_count {
+                best_vote = *vote;
+                best_count = count;
+            }
+        }
+        best_vote
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Model;
+
+    #[test]
+    fn large_unscaled_features_find_themselves() {
+        // Close points far from the origin, where |a|^2 - 2 a.b + |b|^2 loses
+        // every significant digit in f32
+        let data: Vec<ProcessedPatientRecord> = (0..20)
+            .map(|i| ProcessedPatientRecord {
+                features: vec![10_000.0 + i as f32 * 0.5, 20_000.0 - i as f32 * 0.25],
+                target: (i % 2) as u8,
+                site: None,
+            })
+            .collect();
+        let mut model = KNN::new(1);
+        model.train(&data).unwrap();
+        let predictions = model.predict_batch(&data).unwrap();
+        let targets: Vec<u8> = data.iter().map(|record| record.target).collect();
+        assert_eq!(predictions, targets);
     }
 }
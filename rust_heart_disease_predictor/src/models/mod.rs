@@ -1,11 +1,74 @@
+use crate::error::{PredictorError, Result};
 use crate::preprocessing::ProcessedPatientRecord;
 
 pub mod logistic_regression;
 pub mod naive_bayes;
 pub mod knn;
 pub mod decision_tree;
+pub mod optimization;
 
 pub trait Model {
-    fn train(&mut self, training_data: &[ProcessedPatientRecord]);
-    fn predict(&self, record: &ProcessedPatientRecord) -> u8;
-}
\ No newline at end of file
+    fn train(&mut self, training_data: &[ProcessedPatientRecord]) -> Result<()>;
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8>;
+
+    /// Class probabilities indexed by class label, so `probabilities[1]` is the
+    /// estimated risk of class 1. Labels above the largest one the model has
+    /// seen are absent rather than zero-padded; use `probability_of` to read a
+    /// label that may be missing.
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>>;
+
+    /// Predicts every record of a split, in order. Models with a vectorised
+    /// path override this; the default calls `predict` on each record.
+    fn predict_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<u8>> {
+        records.iter().map(|record| self.predict(record)).collect()
+    }
+
+    /// Class probabilities for every record of a split, in order.
+    fn predict_proba_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<Vec<f32>>> {
+        records.iter().map(|record| self.predict_proba(record)).collect()
+    }
+}
+
+/// The probability of `class`, or zero if the vector does not reach it.
+pub fn probability_of(probabilities: &[f32], class: u8) -> f32 {
+    probabilities.get(class as usize).copied().unwrap_or(0.0)
+}
+
+/// The most probable class label; ties go to the smaller label.
+pub fn most_probable(probabilities: &[f32]) -> u8 {
+    let mut best = 0;
+    for (class, &probability) in probabilities.iter().enumerate() {
+        if probability > probabilities[best] {
+            best = class;
+        }
+    }
+    best as u8
+}
+
+/// Checks that a training set is non-empty, that every record has the same
+/// number of features and that none is missing. Returns the feature count.
+pub(crate) fn check_training_data(data: &[ProcessedPatientRecord]) -> Result<usize> {
+    let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+    for record in data {
+        check_record(record, num_features)?;
+    }
+    Ok(num_features)
+}
+
+/// Checks that a record has `num_features` features and no missing values.
+pub(crate) fn check_record(record: &ProcessedPatientRecord, num_features: usize) -> Result<()> {
+    if record.features.len() != num_features {
+        return Err(PredictorError::SchemaMismatch(format!(
+            "expected {} features, found {}",
+            num_features,
+            record.features.len()
+        )));
+    }
+    if let Some(i) = record.features.iter().position(|value| !value.is_finite()) {
+        return Err(PredictorError::InvalidInput(format!(
+            "feature {} is missing or not finite; impute it before training or prediction",
+            i
+        )));
+    }
+    Ok(())
+}
@@ -1,5 +1,6 @@
+use crate::error::{PredictorError, Result};
 use crate::preprocessing::ProcessedPatientRecord;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 #[derive(Default)]
 struct ClassStats {
@@ -9,16 +10,15 @@ struct ClassStats {
 }
 
 pub struct GaussianNB {
-    stats: HashMap<u8, ClassStats>,
+    stats: BTreeMap<u8, ClassStats>,
 }
 
 impl super::Model for GaussianNB {
-    fn train(&mut self, data: &[ProcessedPatientRecord]) {
-        if data.is_empty() {
-            return;
-        }
+    fn train(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        super::check_training_data(data)?;
+        self.stats.clear();
 
-        let mut separated_by_class: HashMap<u8, Vec<&ProcessedPatientRecord>> = HashMap::new();
+        let mut separated_by_class: BTreeMap<u8, Vec<&ProcessedPatientRecord>> = BTreeMap::new();
         for record in data {
             separated_by_class
                 .entry(record.target)
@@ -28,9 +28,10 @@ impl super::Model for GaussianNB {
 
         for (class_value, class_data) in separated_by_class.iter() {
             let num_features = class_data[0].features.len();
-            let mut class_stats = ClassStats::default();
-
-            class_stats.prior = class_data.len() as f32 / data.len() as f32;
+            let mut class_stats = ClassStats {
+                prior: class_data.len() as f32 / data.len() as f32,
+                ..Default::default()
+            };
 
             for i in 0..num_features {
                 let feature_values: Vec<f32> = class_data.iter().map(|r| r.features[i]).collect();
@@ -38,52 +39,119 @@ impl super::Model for GaussianNB {
                 let mean = sum / feature_values.len() as f32;
                 class_stats.mean.push(mean);
 
+                // A class with a single record gets zero variance rather than dividing by zero
                 let variance: f32 = feature_values
                     .iter()
                     .map(|x| (x - mean).powi(2))
                     .sum::<f32>()
-                    / (feature_values.len() - 1) as f32;
+                    / (feature_values.len().max(2) - 1) as f32;
                 class_stats.variance.push(variance + 1e-9);
             }
             self.stats.insert(*class_value, class_stats);
         }
+        Ok(())
     }
 
-    fn predict(&self, record: &ProcessedPatientRecord) -> u8 {
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8> {
         let mut best_class = 0;
         let mut max_posterior = f32::NEG_INFINITY;
 
+        for (class_value, posterior) in self.log_posteriors(record)? {
+            if posterior > max_posterior {
+                max_posterior = posterior;
+                best_class = class_value;
+            }
+        }
+        Ok(best_class)
+    }
+
+    /// Posteriors normalised over the classes seen in training.
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>> {
+        let log_posteriors = self.log_posteriors(record)?;
+
+        // Subtract the largest log posterior so the exponentials cannot underflow to zero
+        let max = log_posteriors.iter().map(|&(_, p)| p).fold(f32::NEG_INFINITY, f32::max);
+        let total: f32 = log_posteriors.iter().map(|&(_, p)| (p - max).exp()).sum();
+
+        let largest_class = log_posteriors.iter().map(|&(class, _)| class).max().unwrap_or(0);
+        let mut probabilities = vec![0.0; largest_class as usize + 1];
+        for (class_value, posterior) in log_posteriors {
+            probabilities[class_value as usize] = (posterior - max).exp() / total;
+        }
+        Ok(probabilities)
+    }
+}
+
+impl Default for GaussianNB {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GaussianNB {
+    pub fn new() -> Self {
+        GaussianNB {
+            stats: BTreeMap::new(),
+        }
+    }
+
+    // Unnormalised log posterior of each class, in class order.
+    fn log_posteriors(&self, record: &ProcessedPatientRecord) -> Result<Vec<(u8, f32)>> {
+        let num_features = match self.stats.values().next() {
+            Some(class_stats) => class_stats.mean.len(),
+            None => return Err(PredictorError::NotFitted("GaussianNB")),
+        };
+        super::check_record(record, num_features)?;
+
+        let mut posteriors = Vec::with_capacity(self.stats.len());
         for (class_value, class_stats) in self.stats.iter() {
             let mut posterior = class_stats.prior.ln();
             for i in 0..record.features.len() {
-                let likelihood = Self::calculate_likelihood(
+                posterior += Self::log_likelihood(
                     record.features[i],
                     class_stats.mean[i],
                     class_stats.variance[i],
                 );
-                // Add a small epsilon to avoid log(0)
-                let log_likelihood = (likelihood + 1e-10).ln();
-                posterior += log_likelihood;
-            }
-
-            if posterior > max_posterior {
-                max_posterior = posterior;
-                best_class = *class_value;
             }
+            posteriors.push((*class_value, posterior));
         }
-        best_class
+        Ok(posteriors)
+    }
+
+    // Gaussian log-density, computed directly so records far from every class
+    // mean still rank the classes instead of all underflowing to the same value.
+    fn log_likelihood(x: f32, mean: f32, variance: f32) -> f32 {
+        -0.5 * (2.0 * std::f32::consts::PI * variance).ln() - (x - mean).powi(2) / (2.0 * variance)
     }
 }
 
-impl GaussianNB {
-    pub fn new() -> Self {
-        GaussianNB {
-            stats: HashMap::new(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Model;
+
+    fn record(x: f32, target: u8) -> ProcessedPatientRecord {
+        ProcessedPatientRecord {
+            features: vec![x],
+            target,
+            site: None,
         }
     }
 
-    fn calculate_likelihood(x: f32, mean: f32, variance: f32) -> f32 {
-        let exponent = -((x - mean).powi(2)) / (2.0 * variance);
-        (1.0 / (2.0 * std::f32::consts::PI * variance).sqrt()) * exponent.exp()
+    #[test]
+    fn far_out_records_keep_distinct_posteriors() {
+        let data: Vec<ProcessedPatientRecord> = [0.0, 0.5, 1.0]
+            .iter()
+            .map(|&x| record(x, 0))
+            .chain([10.0, 10.5, 11.0].iter().map(|&x| record(x, 1)))
+            .collect();
+        let mut model = GaussianNB::new();
+        model.train(&data).unwrap();
+
+        let far = model.predict_proba(&record(100.0, 0)).unwrap();
+        assert!(far[1] > 0.999, "{:?}", far);
+        let near = model.predict_proba(&record(7.0, 0)).unwrap();
+        assert!(near[1] > 0.99, "{:?}", near);
+        assert!((near[0] + near[1] - 1.0).abs() < 1e-6);
     }
 }
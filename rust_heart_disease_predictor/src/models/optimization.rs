@@ -0,0 +1,269 @@
+use serde::Serialize;
+
+use crate::error::{PredictorError, Result};
+
+/// How many training records each gradient step averages over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Batching {
+    /// One step per epoch on the whole training split.
+    Full,
+    /// One step per batch of this many records.
+    MiniBatch(usize),
+    /// One step per record.
+    Stochastic,
+}
+
+impl Batching {
+    pub(crate) fn batch_size(self, records: usize) -> Result<usize> {
+        match self {
+            Batching::Full => Ok(records.max(1)),
+            Batching::MiniBatch(0) => Err(PredictorError::InvalidHyperparameter(
+                "mini-batches need at least 1 record".to_string(),
+            )),
+            Batching::MiniBatch(size) => Ok(size),
+            Batching::Stochastic => Ok(1),
+        }
+    }
+}
+
+/// The rule that turns a gradient into a weight update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Optimizer {
+    /// Steps against the gradient.
+    Sgd,
+    /// Steps against a running sum of gradients, each decayed by `beta`.
+    Momentum { beta: f32 },
+    /// Adam: steps scaled per weight by bias-corrected moving averages of the
+    /// gradient and its square.
+    Adam { beta1: f32, beta2: f32, epsilon: f32 },
+}
+
+impl Optimizer {
+    pub fn momentum() -> Self {
+        Optimizer::Momentum { beta: 0.9 }
+    }
+
+    /// The usual β₁ = 0.9, β₂ = 0.999 and ε = 1e-8.
+    pub fn adam() -> Self {
+        Optimizer::Adam {
+            beta1: 0.9,
+            beta2: 0.999,
+            epsilon: 1e-8,
+        }
+    }
+
+    pub(crate) fn validate(self) -> Result<()> {
+        match self {
+            Optimizer::Sgd => Ok(()),
+            Optimizer::Momentum { beta } => check_decay(beta),
+            Optimizer::Adam { beta1, beta2, epsilon } => {
+                check_decay(beta1)?;
+                check_decay(beta2)?;
+                if !(epsilon > 0.0 && epsilon.is_finite()) {
+                    return Err(PredictorError::InvalidHyperparameter(format!(
+                        "Adam epsilon must be positive, got {}",
+                        epsilon
+                    )));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn check_decay(decay: f32) -> Result<()> {
+    if (0.0..1.0).contains(&decay) {
+        Ok(())
+    } else {
+        Err(PredictorError::InvalidHyperparameter(format!(
+            "decay rates must be in [0, 1), got {}",
+            decay
+        )))
+    }
+}
+
+/// How the learning rate changes from one epoch to the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LearningRateSchedule {
+    Constant,
+    /// Multiplies the rate by `factor` every `every` epochs.
+    StepDecay { every: usize, factor: f32 },
+    /// Multiplies the rate by `rate` every epoch.
+    Exponential { rate: f32 },
+    /// `initial / (1 + decay * epoch)`.
+    InverseTime { decay: f32 },
+}
+
+impl LearningRateSchedule {
+    /// The learning rate for `epoch`, counted from zero.
+    pub fn rate(self, initial: f32, epoch: usize) -> f32 {
+        match self {
+            LearningRateSchedule::Constant => initial,
+            LearningRateSchedule::StepDecay { every, factor } => initial * factor.powi((epoch / every.max(1)) as i32),
+            LearningRateSchedule::Exponential { rate } => initial * rate.powi(epoch as i32),
+            LearningRateSchedule::InverseTime { decay } => initial / (1.0 + decay * epoch as f32),
+        }
+    }
+
+    pub(crate) fn validate(self) -> Result<()> {
+        let valid = match self {
+            LearningRateSchedule::Constant => true,
+            LearningRateSchedule::StepDecay { every, factor } => every > 0 && factor > 0.0 && factor <= 1.0,
+            LearningRateSchedule::Exponential { rate } => rate > 0.0 && rate <= 1.0,
+            LearningRateSchedule::InverseTime { decay } => decay >= 0.0 && decay.is_finite(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(PredictorError::InvalidHyperparameter(format!(
+                "invalid learning rate schedule {:?}",
+                self
+            )))
+        }
+    }
+}
+
+/// When to end training before the last epoch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EarlyStopping {
+    /// Always run every epoch.
+    None,
+    /// Stop once the training loss has not improved on its best value by at
+    /// least `tolerance` for `patience` epochs in a row.
+    Tolerance { tolerance: f32, patience: usize },
+    /// Hold out `validation_fraction` of the training split, stratified, and
+    /// stop once the loss on it has not improved on its best value by at
+    /// least `tolerance` for `patience` epochs in a row. The weights of the
+    /// best epoch are kept.
+    ValidationPlateau { validation_fraction: f32, tolerance: f32, patience: usize },
+}
+
+impl EarlyStopping {
+    /// A tolerance of 1e-4 with a patience of 5 epochs.
+    pub fn tolerance() -> Self {
+        EarlyStopping::Tolerance {
+            tolerance: 1e-4,
+            patience: 5,
+        }
+    }
+
+    /// 10% of the training split held out, a tolerance of 1e-4 and a patience of 10 epochs.
+    pub fn validation_plateau() -> Self {
+        EarlyStopping::ValidationPlateau {
+            validation_fraction: 0.1,
+            tolerance: 1e-4,
+            patience: 10,
+        }
+    }
+
+    pub(crate) fn validate(self) -> Result<()> {
+        match self {
+            EarlyStopping::ValidationPlateau { validation_fraction, .. }
+                if !(validation_fraction > 0.0 && validation_fraction < 1.0) =>
+            {
+                Err(PredictorError::InvalidHyperparameter(format!(
+                    "validation fraction must be in (0, 1), got {}",
+                    validation_fraction
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The loss after one epoch of training.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EpochLoss {
+    pub epoch: usize,
+    pub training_loss: f32,
+    /// Only recorded when stopping on a validation plateau.
+    pub validation_loss: Option<f32>,
+}
+
+/// Tracks the best loss seen so far for early stopping.
+#[derive(Debug, Clone)]
+pub(crate) struct Plateau {
+    tolerance: f32,
+    patience: usize,
+    best: f32,
+    epochs_without_improvement: usize,
+}
+
+impl Plateau {
+    pub(crate) fn new(tolerance: f32, patience: usize) -> Self {
+        Plateau {
+            tolerance,
+            patience,
+            best: f32::INFINITY,
+            epochs_without_improvement: 0,
+        }
+    }
+
+    /// Records a loss and returns whether it is the best so far.
+    pub(crate) fn record(&mut self, loss: f32) -> bool {
+        if loss < self.best - self.tolerance {
+            self.best = loss;
+            self.epochs_without_improvement = 0;
+            true
+        } else {
+            self.epochs_without_improvement += 1;
+            false
+        }
+    }
+
+    pub(crate) fn is_stalled(&self) -> bool {
+        self.epochs_without_improvement >= self.patience.max(1)
+    }
+}
+
+/// The per-weight state an optimizer carries from one step to the next.
+#[derive(Debug, Clone)]
+pub(crate) struct OptimizerState {
+    optimizer: Optimizer,
+    velocity: Vec<f32>,
+    squared: Vec<f32>,
+    steps: i32,
+}
+
+impl OptimizerState {
+    pub(crate) fn new(optimizer: Optimizer, num_weights: usize) -> Self {
+        OptimizerState {
+            optimizer,
+            velocity: vec![0.0; num_weights],
+            squared: vec![0.0; num_weights],
+            steps: 0,
+        }
+    }
+
+    /// Moves the weights one step against the gradient.
+    pub(crate) fn step(&mut self, weights: &mut [f32], gradient: &[f32], learning_rate: f32) {
+        self.steps += 1;
+        match self.optimizer {
+            Optimizer::Sgd => {
+                for (weight, g) in weights.iter_mut().zip(gradient) {
+                    *weight -= learning_rate * g;
+                }
+            }
+            Optimizer::Momentum { beta } => {
+                for ((weight, velocity), g) in weights.iter_mut().zip(&mut self.velocity).zip(gradient) {
+                    *velocity = beta * *velocity + g;
+                    *weight -= learning_rate * *velocity;
+                }
+            }
+            Optimizer::Adam { beta1, beta2, epsilon } => {
+                let correction1 = 1.0 - beta1.powi(self.steps);
+                let correction2 = 1.0 - beta2.powi(self.steps);
+                for (((weight, mean), squared), g) in weights
+                    .iter_mut()
+                    .zip(&mut self.velocity)
+                    .zip(&mut self.squared)
+                    .zip(gradient)
+                {
+                    *mean = beta1 * *mean + (1.0 - beta1) * g;
+                    *squared = beta2 * *squared + (1.0 - beta2) * g * g;
+                    *weight -= learning_rate * (*mean / correction1) / ((*squared / correction2).sqrt() + epsilon);
+                }
+            }
+        }
+    }
+}
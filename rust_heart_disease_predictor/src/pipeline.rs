@@ -0,0 +1,70 @@
+use crate::error::Result;
+use crate::models::Model;
+use crate::preprocessing::{ProcessedPatientRecord, Transformer};
+
+/// A chain of preprocessing steps followed by a model. Training fits each step
+/// on the output of the previous one, so every parameter is learned from the
+/// training data alone; prediction replays the fitted steps on each record.
+pub struct Pipeline {
+    steps: Vec<Box<dyn Transformer>>,
+    model: Box<dyn Model>,
+}
+
+impl Pipeline {
+    pub fn new(steps: Vec<Box<dyn Transformer>>, model: Box<dyn Model>) -> Self {
+        Pipeline { steps, model }
+    }
+
+    /// Names of the features the model sees, given the names of the raw input features.
+    pub fn feature_names(&self, input: &[String]) -> Vec<String> {
+        self.steps
+            .iter()
+            .fold(input.to_vec(), |names, step| step.feature_names(&names))
+    }
+
+    /// Applies the fitted steps to a split without the final model, one record
+    /// at a time as prediction does. Steps that drop rows from a whole split,
+    /// such as outlier removal or `DropRows` imputation, keep every record
+    /// here, so the output lines up with the input's targets.
+    pub fn transform(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        self.transform_records(data)
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.steps
+            .iter()
+            .try_fold(record.clone(), |record, step| step.transform_record(&record))
+    }
+
+    fn transform_records(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        records.iter().map(|record| self.transform_record(record)).collect()
+    }
+}
+
+impl Model for Pipeline {
+    fn train(&mut self, training_data: &[ProcessedPatientRecord]) -> Result<()> {
+        let mut transformed = training_data.to_vec();
+        for step in &mut self.steps {
+            transformed = step.fit_transform(&transformed)?;
+        }
+        self.model.train(&transformed)
+    }
+
+    fn predict(&self, record: &ProcessedPatientRecord) -> Result<u8> {
+        self.model.predict(&self.transform_record(record)?)
+    }
+
+    fn predict_proba(&self, record: &ProcessedPatientRecord) -> Result<Vec<f32>> {
+        self.model.predict_proba(&self.transform_record(record)?)
+    }
+
+    // Records go through `transform_record` one by one, since a whole-split
+    // `transform` may drop rows and every record needs a prediction
+    fn predict_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<u8>> {
+        self.model.predict_batch(&self.transform_records(records)?)
+    }
+
+    fn predict_proba_batch(&self, records: &[ProcessedPatientRecord]) -> Result<Vec<Vec<f32>>> {
+        self.model.predict_proba_batch(&self.transform_records(records)?)
+    }
+}
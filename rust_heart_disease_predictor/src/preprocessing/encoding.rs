@@ -0,0 +1,182 @@
+use super::schema::FeatureKind;
+use super::{Dataset, ProcessedPatientRecord, Transformer};
+use crate::error::{PredictorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Expand each column into one indicator column per category seen in training.
+    /// A category never seen in training sets every indicator to zero.
+    OneHot,
+    /// Replace each code by its rank among the categories seen in training.
+    /// A category never seen in training takes the rank of the nearest known category.
+    Ordinal,
+}
+
+/// Encodes the chosen categorical feature columns. Categories are learned from
+/// the training split by `fit`. Missing (`NaN`) values stay missing.
+pub struct CategoricalEncoder {
+    columns: Vec<usize>,
+    encoding: Encoding,
+    categories: Vec<Vec<f32>>,
+    num_features: Option<usize>,
+}
+
+impl CategoricalEncoder {
+    pub fn new(mut columns: Vec<usize>, encoding: Encoding) -> Self {
+        columns.sort_unstable();
+        columns.dedup();
+        CategoricalEncoder {
+            columns,
+            encoding,
+            categories: Vec::new(),
+            num_features: None,
+        }
+    }
+
+    /// Encodes every column the dataset declares as categorical.
+    pub fn for_categorical(dataset: &Dataset, encoding: Encoding) -> Self {
+        let columns = dataset
+            .feature_kinds
+            .iter()
+            .enumerate()
+            .filter(|(_, kind)| **kind == FeatureKind::Categorical)
+            .map(|(i, _)| i)
+            .collect();
+        Self::new(columns, encoding)
+    }
+
+    /// Sorted categories learned for each encoded column.
+    pub fn categories(&self) -> &[Vec<f32>] {
+        &self.categories
+    }
+}
+
+impl Transformer for CategoricalEncoder {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            super::check_width(record, num_features)?;
+        }
+        if let Some(&column) = self.columns.iter().find(|&&column| column >= num_features) {
+            return Err(PredictorError::SchemaMismatch(format!(
+                "cannot encode column {} of records with {} features",
+                column, num_features
+            )));
+        }
+
+        self.categories = self
+            .columns
+            .iter()
+            .map(|&column| {
+                let mut values: Vec<f32> = data
+                    .iter()
+                    .map(|record| record.features[column])
+                    .filter(|value| !value.is_nan())
+                    .collect();
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                values.dedup();
+                values
+            })
+            .collect();
+        self.num_features = Some(num_features);
+        Ok(())
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        let num_features = self.num_features.ok_or(PredictorError::NotFitted("CategoricalEncoder"))?;
+        super::check_width(record, num_features)?;
+
+        let mut features = Vec::with_capacity(record.features.len());
+        for (i, &value) in record.features.iter().enumerate() {
+            let categories = match self.encoded_position(i) {
+                Some(position) => &self.categories[position],
+                None => {
+                    features.push(value);
+                    continue;
+                }
+            };
+
+            match self.encoding {
+                Encoding::OneHot => {
+                    features.extend(categories.iter().map(|&category| {
+                        if value.is_nan() {
+                            f32::NAN
+                        } else if value == category {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    }));
+                }
+                Encoding::Ordinal => features.push(Self::rank(categories, value)),
+            }
+        }
+
+        Ok(ProcessedPatientRecord {
+            features,
+            target: record.target,
+            site: record.site.clone(),
+        })
+    }
+
+    /// Output column names for the given input names. One-hot columns are
+    /// named `feature=category`.
+    fn feature_names(&self, input: &[String]) -> Vec<String> {
+        let mut names = Vec::new();
+        for (i, name) in input.iter().enumerate() {
+            match (self.encoded_position(i), self.encoding) {
+                (Some(position), Encoding::OneHot) => {
+                    names.extend(
+                        self.categories[position]
+                            .iter()
+                            .map(|category| format!("{}={}", name, category)),
+                    );
+                }
+                _ => names.push(name.clone()),
+            }
+        }
+        names
+    }
+}
+
+impl CategoricalEncoder {
+    /// Encodes a whole dataset, updating its feature names and kinds.
+    pub fn transform_dataset(&self, dataset: &Dataset) -> Result<Dataset> {
+        let records = self.transform(&dataset.records)?;
+        let mut feature_kinds = Vec::new();
+        for (i, &kind) in dataset.feature_kinds.iter().enumerate() {
+            match (self.encoded_position(i), self.encoding) {
+                (Some(position), Encoding::OneHot) => {
+                    feature_kinds.extend(std::iter::repeat_n(FeatureKind::Categorical, self.categories[position].len()));
+                }
+                (Some(_), Encoding::Ordinal) => feature_kinds.push(FeatureKind::Ordinal),
+                (None, _) => feature_kinds.push(kind),
+            }
+        }
+
+        Ok(Dataset {
+            feature_names: self.feature_names(&dataset.feature_names),
+            feature_kinds,
+            records,
+        })
+    }
+
+    // Names and kinds pass through unchanged until the encoder has been fitted.
+    fn encoded_position(&self, column: usize) -> Option<usize> {
+        self.num_features?;
+        self.columns.binary_search(&column).ok()
+    }
+
+    fn rank(categories: &[f32], value: f32) -> f32 {
+        if value.is_nan() || categories.is_empty() {
+            return f32::NAN;
+        }
+        let nearest = categories
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - value).abs().partial_cmp(&(*b - value).abs()).unwrap())
+            .map(|(rank, _)| rank)
+            .unwrap_or(0);
+        nearest as f32
+    }
+}
@@ -0,0 +1,231 @@
+use super::{ProcessedPatientRecord, Transformer, check_width};
+use crate::error::{PredictorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImputationStrategy {
+    /// Remove any record with a missing value.
+    DropRows,
+    Mean,
+    Median,
+    MostFrequent,
+    Constant(f32),
+}
+
+/// Number of values filled in for each feature, and rows removed under `DropRows`.
+#[derive(Debug, Clone, Default)]
+pub struct ImputationReport {
+    pub imputed_per_feature: Vec<usize>,
+    pub dropped_rows: usize,
+}
+
+impl ImputationReport {
+    pub fn total_imputed(&self) -> usize {
+        self.imputed_per_feature.iter().sum()
+    }
+}
+
+/// Fills missing (`NaN`) feature values with statistics learned from the training split.
+pub struct Imputer {
+    strategy: ImputationStrategy,
+    column_strategies: Vec<(usize, ImputationStrategy)>,
+    fill_values: Vec<f32>,
+}
+
+impl Imputer {
+    pub fn new(strategy: ImputationStrategy) -> Self {
+        Imputer {
+            strategy,
+            column_strategies: Vec::new(),
+            fill_values: Vec::new(),
+        }
+    }
+
+    /// Uses `strategy` instead of the default for the given columns, e.g. the
+    /// most frequent code for categorical columns, whose median may not be a
+    /// category at all.
+    pub fn with_column_strategy(mut self, columns: &[usize], strategy: ImputationStrategy) -> Self {
+        self.column_strategies.extend(columns.iter().map(|&column| (column, strategy)));
+        self
+    }
+
+    fn strategy_for(&self, column: usize) -> ImputationStrategy {
+        self.column_strategies
+            .iter()
+            .rev()
+            .find(|(c, _)| *c == column)
+            .map_or(self.strategy, |(_, strategy)| *strategy)
+    }
+
+    pub fn fill_values(&self) -> &[f32] {
+        &self.fill_values
+    }
+
+    /// Transforms a split and reports how many values were filled in per feature.
+    pub fn transform_with_report(
+        &self,
+        data: &[ProcessedPatientRecord],
+    ) -> Result<(Vec<ProcessedPatientRecord>, ImputationReport)> {
+        if self.fill_values.is_empty() {
+            return Err(PredictorError::NotFitted("Imputer"));
+        }
+        let mut report = ImputationReport {
+            imputed_per_feature: vec![0; self.fill_values.len()],
+            dropped_rows: 0,
+        };
+
+        let mut records = Vec::with_capacity(data.len());
+        for record in data {
+            check_width(record, self.fill_values.len())?;
+            let dropped = record
+                .features
+                .iter()
+                .enumerate()
+                .any(|(i, value)| value.is_nan() && self.strategy_for(i) == ImputationStrategy::DropRows);
+            if dropped {
+                report.dropped_rows += 1;
+                continue;
+            }
+
+            for (i, (count, value)) in report.imputed_per_feature.iter_mut().zip(record.features.iter()).enumerate() {
+                if value.is_nan() && self.strategy_for(i) != ImputationStrategy::DropRows {
+                    *count += 1;
+                }
+            }
+            records.push(self.transform_record(record)?);
+        }
+
+        Ok((records, report))
+    }
+
+    fn median(values: &mut [f32]) -> f32 {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mid = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) / 2.0
+        } else {
+            values[mid]
+        }
+    }
+
+    // Ties are broken towards the smallest value.
+    fn most_frequent(values: &mut [f32]) -> f32 {
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mut best_value = values[0];
+        let mut best_count = 0;
+        for run in values.chunk_by(|a, b| a == b) {
+            if run.len() > best_count {
+                best_count = run.len();
+                best_value = run[0];
+            }
+        }
+        best_value
+    }
+}
+
+impl Transformer for Imputer {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            check_width(record, num_features)?;
+        }
+        if let Some(&(column, _)) = self.column_strategies.iter().find(|(column, _)| *column >= num_features) {
+            return Err(PredictorError::SchemaMismatch(format!(
+                "cannot impute column {} of records with {} features",
+                column, num_features
+            )));
+        }
+
+        self.fill_values = (0..num_features)
+            .map(|i| {
+                let mut observed: Vec<f32> = data
+                    .iter()
+                    .map(|record| record.features[i])
+                    .filter(|value| !value.is_nan())
+                    .collect();
+
+                match self.strategy_for(i) {
+                    ImputationStrategy::DropRows => f32::NAN,
+                    ImputationStrategy::Constant(value) => value,
+                    // A feature with no observed values falls back to zero
+                    _ if observed.is_empty() => 0.0,
+                    ImputationStrategy::Mean => observed.iter().sum::<f32>() / observed.len() as f32,
+                    ImputationStrategy::Median => Self::median(&mut observed),
+                    ImputationStrategy::MostFrequent => Self::most_frequent(&mut observed),
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Fills a single record, e.g. an incoming patient at inference time.
+    /// Under `DropRows` the record is returned unchanged, and models reject it
+    /// if it still has gaps.
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        if self.fill_values.is_empty() {
+            return Err(PredictorError::NotFitted("Imputer"));
+        }
+        check_width(record, self.fill_values.len())?;
+
+        let mut record = record.clone();
+        for (value, fill) in record.features.iter_mut().zip(self.fill_values.iter()) {
+            if value.is_nan() {
+                *value = *fill;
+            }
+        }
+        Ok(record)
+    }
+
+    fn transform(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        Ok(self.transform_with_report(data)?.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_missing_column_uses_constant_and_keeps_drop_rows_gaps() {
+        let data = vec![ProcessedPatientRecord {
+            features: vec![f32::NAN, 1.0],
+            target: 0,
+            site: None,
+        }];
+
+        let mut constant = Imputer::new(ImputationStrategy::Constant(-1.0));
+        constant.fit(&data).unwrap();
+        assert_eq!(constant.transform_record(&data[0]).unwrap().features, vec![-1.0, 1.0]);
+
+        let mut drop_rows = Imputer::new(ImputationStrategy::DropRows);
+        drop_rows.fit(&data).unwrap();
+        assert!(drop_rows.transform_record(&data[0]).unwrap().features[0].is_nan());
+
+        let mut median = Imputer::new(ImputationStrategy::Median);
+        median.fit(&data).unwrap();
+        assert_eq!(median.transform_record(&data[0]).unwrap().features, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn column_strategy_keeps_categorical_fills_within_the_codes() {
+        let data: Vec<ProcessedPatientRecord> = [[3.0, 120.0], [7.0, 130.0], [3.0, 140.0], [7.0, 150.0], [f32::NAN, f32::NAN]]
+            .iter()
+            .map(|row| ProcessedPatientRecord {
+                features: row.to_vec(),
+                target: 0,
+                site: None,
+            })
+            .collect();
+
+        let mut imputer =
+            Imputer::new(ImputationStrategy::Median).with_column_strategy(&[0], ImputationStrategy::MostFrequent);
+        imputer.fit(&data).unwrap();
+        assert_eq!(imputer.fill_values(), &[3.0, 135.0]);
+
+        let mut dropping =
+            Imputer::new(ImputationStrategy::Median).with_column_strategy(&[0], ImputationStrategy::DropRows);
+        dropping.fit(&data).unwrap();
+        let (records, report) = dropping.transform_with_report(&data).unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(report.dropped_rows, 1);
+    }
+}
@@ -0,0 +1,307 @@
+use csv::{ReaderBuilder, StringRecord};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+
+use crate::error::{PredictorError, Result};
+
+pub mod encoding;
+pub mod imputer;
+pub mod outliers;
+pub mod pca;
+pub mod resampling;
+pub mod scaling;
+pub mod schema;
+pub mod split;
+pub mod validation;
+
+use schema::{ColumnSpec, DatasetSchema, FeatureKind};
+
+/// Site names and file names of the four processed UCI heart-disease datasets.
+/// All four share the 14-column Cleveland layout.
+pub const UCI_SITES: [(&str, &str); 4] = [
+    ("Cleveland", "processed.cleveland.data"),
+    ("Hungarian", "processed.hungarian.data"),
+    ("Switzerland", "processed.switzerland.data"),
+    ("VA Long Beach", "processed.va.data"),
+];
+
+/// A cleaned record. Missing feature values are stored as `f32::NAN` until an
+/// `Imputer` fills them in. `site` names the hospital the record came from when
+/// it was loaded through `load_sites`.
+#[derive(Debug, Clone)]
+pub struct ProcessedPatientRecord {
+    pub features: Vec<f32>,
+    pub target: u8,
+    pub site: Option<String>,
+}
+
+/// A preprocessing step whose parameters are learned from the training split
+/// by `fit` and then applied unchanged to test and inference records.
+pub trait Transformer {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()>;
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord>;
+
+    /// Transforms a whole split. Steps that remove rows override this.
+    fn transform(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        data.iter().map(|record| self.transform_record(record)).collect()
+    }
+
+    /// Fits on a training split and transforms it. Steps that should only
+    /// change the training split, such as resampling, override this.
+    fn fit_transform(&mut self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        self.fit(data)?;
+        self.transform(data)
+    }
+
+    /// Output feature names for the given input names.
+    fn feature_names(&self, input: &[String]) -> Vec<String> {
+        input.to_vec()
+    }
+}
+
+/// Checks that a record has the number of features a fitted step expects.
+pub(crate) fn check_width(record: &ProcessedPatientRecord, expected: usize) -> Result<()> {
+    if record.features.len() != expected {
+        return Err(PredictorError::SchemaMismatch(format!(
+            "expected {} features, found {}",
+            expected,
+            record.features.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Records together with the names and kinds of their feature columns.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub feature_names: Vec<String>,
+    pub feature_kinds: Vec<FeatureKind>,
+    pub records: Vec<ProcessedPatientRecord>,
+}
+
+impl Dataset {
+    pub fn from_schema(schema: &DatasetSchema, records: Vec<ProcessedPatientRecord>) -> Self {
+        Dataset {
+            feature_names: schema.feature_columns().map(|column| column.name.clone()).collect(),
+            feature_kinds: schema.feature_columns().map(|column| column.kind).collect(),
+            records,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn feature_index(&self, name: &str) -> Option<usize> {
+        self.feature_names.iter().position(|feature| feature == name)
+    }
+
+    /// A dataset with the same feature columns holding the given records.
+    pub fn with_records(&self, records: Vec<ProcessedPatientRecord>) -> Dataset {
+        Dataset {
+            feature_names: self.feature_names.clone(),
+            feature_kinds: self.feature_kinds.clone(),
+            records,
+        }
+    }
+}
+
+fn parse_value(raw: &str, column: &ColumnSpec, schema: &DatasetSchema, line: u64) -> Result<f32> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw == schema.missing_token {
+        return Ok(f32::NAN);
+    }
+    if let Ok(value) = raw.parse::<f32>() {
+        // "NaN", "inf" and out-of-range numbers parse but are not measurements
+        if !value.is_finite() {
+            return Err(PredictorError::Parse {
+                line: Some(line),
+                message: format!("non-finite value '{}' in column '{}'", raw, column.name),
+            });
+        }
+        if value == 0.0 && column.zero_is_missing {
+            return Ok(f32::NAN);
+        }
+        return Ok(value);
+    }
+    if let Some(code) = column.categories.iter().position(|category| category == raw) {
+        return Ok(code as f32);
+    }
+    Err(PredictorError::Parse {
+        line: Some(line),
+        message: format!("cannot parse '{}' in column '{}'", raw, column.name),
+    })
+}
+
+// Maps each schema column to its field position in the file. With a header,
+// columns are located by name; otherwise they are read in schema order.
+fn column_positions(schema: &DatasetSchema, header: Option<&StringRecord>) -> Result<Vec<usize>> {
+    match header {
+        Some(header) => schema
+            .columns
+            .iter()
+            .map(|column| {
+                header
+                    .iter()
+                    .position(|field| field.trim() == column.name)
+                    .ok_or_else(|| {
+                        PredictorError::SchemaMismatch(format!("column '{}' not found in header", column.name))
+                    })
+            })
+            .collect(),
+        None => Ok((0..schema.columns.len()).collect()),
+    }
+}
+
+/// Loads a delimited file described by `schema`. Rows with missing feature
+/// values are kept; run an `Imputer` fitted on the training split to fill or
+/// drop them. Unless the schema keeps multi-class targets, any positive target
+/// value becomes class 1.
+pub fn load_dataset(path: &str, schema: &DatasetSchema) -> Result<Dataset> {
+    let target_index = schema.target_index()?;
+
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(schema.has_header)
+        .from_path(path)?;
+
+    let header = if schema.has_header { Some(rdr.headers()?.clone()) } else { None };
+    let positions = column_positions(schema, header.as_ref())?;
+
+    let mut records = Vec::new();
+    for result in rdr.records() {
+        let row = result?;
+        let line = row.position().map_or(0, |position| position.line());
+        if row.len() < schema.columns.len() {
+            return Err(PredictorError::SchemaMismatch(format!(
+                "line {}: expected {} columns, found {}",
+                line,
+                schema.columns.len(),
+                row.len()
+            )));
+        }
+
+        let mut features = Vec::with_capacity(schema.columns.len() - 1);
+        let mut target = f32::NAN;
+        for (i, (column, &position)) in schema.columns.iter().zip(positions.iter()).enumerate() {
+            let value = parse_value(&row[position], column, schema, line)?;
+            if i == target_index {
+                target = value;
+            } else {
+                features.push(value);
+            }
+        }
+
+        if target.is_nan() {
+            return Err(PredictorError::Parse {
+                line: Some(line),
+                message: "missing target value".to_string(),
+            });
+        }
+        if !schema.binarize_target && (target < 0.0 || target > u8::MAX as f32 || target.fract() != 0.0) {
+            return Err(PredictorError::Parse {
+                line: Some(line),
+                message: format!("target value {} is not a class label", target),
+            });
+        }
+
+        records.push(ProcessedPatientRecord {
+            features,
+            target: if schema.binarize_target {
+                if target > 0.0 { 1 } else { 0 }
+            } else {
+                target as u8
+            },
+            site: None,
+        });
+    }
+
+    Ok(Dataset::from_schema(schema, records))
+}
+
+/// Loads a file in the processed UCI heart-disease layout.
+pub fn load_and_preprocess_data(path: &str) -> Result<Vec<ProcessedPatientRecord>> {
+    Ok(load_dataset(path, &DatasetSchema::uci_heart())?.records)
+}
+
+/// Loads several files that share one schema, tagging each record with the
+/// site name paired with its path. Records are returned merged in the order
+/// the sources are given; use `group_by_site` to separate them again.
+pub fn load_sites(sources: &[(&str, &str)], schema: &DatasetSchema) -> Result<Dataset> {
+    let mut records = Vec::new();
+    for &(site, path) in sources {
+        let dataset = load_dataset(path, schema)?;
+        records.extend(dataset.records.into_iter().map(|mut record| {
+            record.site = Some(site.to_string());
+            record
+        }));
+    }
+    Ok(Dataset::from_schema(schema, records))
+}
+
+/// Groups records by site name. Records without a site are grouped under "unknown".
+pub fn group_by_site(records: &[ProcessedPatientRecord]) -> BTreeMap<String, Vec<ProcessedPatientRecord>> {
+    let mut groups: BTreeMap<String, Vec<ProcessedPatientRecord>> = BTreeMap::new();
+    for record in records {
+        let site = record.site.clone().unwrap_or_else(|| "unknown".to_string());
+        groups.entry(site).or_default().push(record.clone());
+    }
+    groups
+}
+
+/// Counts the missing (`NaN`) values of each feature.
+pub fn count_missing(data: &[ProcessedPatientRecord]) -> Vec<usize> {
+    let num_features = data.first().map_or(0, |record| record.features.len());
+    let mut counts = vec![0; num_features];
+    for record in data {
+        for (count, value) in counts.iter_mut().zip(record.features.iter()) {
+            if value.is_nan() {
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
+
+pub fn train_test_split<R: Rng + ?Sized>(
+    data: &mut Vec<ProcessedPatientRecord>,
+    test_size: f32,
+    rng: &mut R,
+) -> (Vec<ProcessedPatientRecord>, Vec<ProcessedPatientRecord>) {
+    data.shuffle(rng);
+    let test_count = (data.len() as f32 * test_size).round() as usize;
+    let test_set = data.drain(..test_count).collect();
+    let train_set = std::mem::take(data);
+    (train_set, test_set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uci_zero_cholesterol_is_missing() {
+        let schema = DatasetSchema::uci_heart();
+        let column = |name: &str| schema.columns.iter().find(|column| column.name == name).unwrap();
+        assert!(parse_value("0", column("chol"), &schema, 1).unwrap().is_nan());
+        assert!(parse_value("0", column("trestbps"), &schema, 1).unwrap().is_nan());
+        assert_eq!(parse_value("0", column("oldpeak"), &schema, 1).unwrap(), 0.0);
+        assert_eq!(parse_value("240", column("chol"), &schema, 1).unwrap(), 240.0);
+    }
+
+    #[test]
+    fn non_finite_values_are_parse_errors() {
+        let schema = DatasetSchema::uci_heart();
+        let age = &schema.columns[0];
+        for raw in ["NaN", "inf", "-inf", "1e40"] {
+            match parse_value(raw, age, &schema, 7) {
+                Err(PredictorError::Parse { line, .. }) => assert_eq!(line, Some(7)),
+                other => panic!("'{}' parsed as {:?}", raw, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::{Rng, SeedableRng};
+
+use super::scaling::quantile;
+use super::{ProcessedPatientRecord, Transformer, check_width};
+use crate::error::{PredictorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierMethod {
+    /// A value outside `[Q1 - k * IQR, Q3 + k * IQR]` of its feature.
+    Iqr { k: f32 },
+    /// A value more than `threshold` standard deviations from its feature's mean.
+    ZScore { threshold: f32 },
+    /// A record whose isolation-forest anomaly score is above `threshold`.
+    /// Each tree is grown on `sample_size` records drawn without replacement.
+    IsolationForest { trees: usize, sample_size: usize, threshold: f32 },
+}
+
+impl OutlierMethod {
+    /// The usual 1.5 × IQR fences.
+    pub fn iqr() -> Self {
+        OutlierMethod::Iqr { k: 1.5 }
+    }
+
+    pub fn z_score() -> Self {
+        OutlierMethod::ZScore { threshold: 3.0 }
+    }
+
+    /// 100 trees of up to 256 records, flagging scores above 0.6.
+    pub fn isolation_forest() -> Self {
+        OutlierMethod::IsolationForest {
+            trees: 100,
+            sample_size: 256,
+            threshold: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlierAction {
+    /// Keep outliers and only report them.
+    Flag,
+    /// Drop outliers when a whole split is transformed.
+    Remove,
+}
+
+/// Records found to be outliers, by their position in the input split.
+/// `outliers_per_feature` counts out-of-fence values for the IQR and z-score
+/// methods and is empty for the isolation forest, which scores whole records.
+#[derive(Debug, Clone, Default)]
+pub struct OutlierReport {
+    pub outliers: Vec<usize>,
+    pub outliers_per_feature: Vec<usize>,
+    pub removed_rows: usize,
+}
+
+#[derive(Debug, Clone)]
+enum IsolationNode {
+    Split {
+        feature: usize,
+        value: f32,
+        left: Box<IsolationNode>,
+        right: Box<IsolationNode>,
+    },
+    Leaf {
+        size: usize,
+    },
+}
+
+impl IsolationNode {
+    fn grow(samples: &[&[f32]], columns: &[usize], depth: usize, max_depth: usize, rng: &mut StdRng) -> Self {
+        if depth >= max_depth || samples.len() <= 1 {
+            return IsolationNode::Leaf { size: samples.len() };
+        }
+
+        // Only features that still vary within this node can split it
+        let ranges: Vec<(usize, f32, f32)> = columns
+            .iter()
+            .filter_map(|&feature| {
+                let min = samples.iter().map(|s| s[feature]).fold(f32::INFINITY, f32::min);
+                let max = samples.iter().map(|s| s[feature]).fold(f32::NEG_INFINITY, f32::max);
+                (max > min).then_some((feature, min, max))
+            })
+            .collect();
+        if ranges.is_empty() {
+            return IsolationNode::Leaf { size: samples.len() };
+        }
+
+        let (feature, min, max) = ranges[rng.gen_range(0..ranges.len())];
+        let value = rng.gen_range(min..max);
+        let (left, right): (Vec<&[f32]>, Vec<&[f32]>) = samples.iter().partition(|s| s[feature] < value);
+        IsolationNode::Split {
+            feature,
+            value,
+            left: Box::new(Self::grow(&left, columns, depth + 1, max_depth, rng)),
+            right: Box::new(Self::grow(&right, columns, depth + 1, max_depth, rng)),
+        }
+    }
+
+    fn path_length(&self, features: &[f32], depth: usize) -> f32 {
+        match self {
+            IsolationNode::Leaf { size } => depth as f32 + average_path_length(*size),
+            IsolationNode::Split {
+                feature,
+                value,
+                left,
+                right,
+            } => {
+                if features[*feature] < *value {
+                    left.path_length(features, depth + 1)
+                } else {
+                    right.path_length(features, depth + 1)
+                }
+            }
+        }
+    }
+}
+
+// Average path length of an unsuccessful search in a binary search tree of
+// `n` records, used to normalise isolation depths.
+fn average_path_length(n: usize) -> f32 {
+    match n {
+        0 | 1 => 0.0,
+        2 => 1.0,
+        _ => {
+            let n = n as f32;
+            2.0 * ((n - 1.0).ln() + 0.577_215_7) - 2.0 * (n - 1.0) / n
+        }
+    }
+}
+
+/// Flags or removes outliers, with fences or an isolation forest learned from
+/// the training split. Outliers are only removed by `transform` on a whole
+/// split; `transform_record` passes records through so an incoming patient is
+/// always scored. Missing values are never treated as outliers by the fence
+/// methods, and the isolation forest needs imputed data.
+#[derive(Debug, Clone)]
+pub struct OutlierDetector {
+    method: OutlierMethod,
+    action: OutlierAction,
+    columns: Option<Vec<usize>>,
+    seed: u64,
+    num_features: usize,
+    lower: Vec<f32>,
+    upper: Vec<f32>,
+    forest: Vec<IsolationNode>,
+    forest_sample_size: usize,
+}
+
+impl OutlierDetector {
+    /// `seed` drives the isolation forest's subsamples and splits.
+    pub fn new(method: OutlierMethod, action: OutlierAction, seed: u64) -> Self {
+        OutlierDetector {
+            method,
+            action,
+            columns: None,
+            seed,
+            num_features: 0,
+            lower: Vec::new(),
+            upper: Vec::new(),
+            forest: Vec::new(),
+            forest_sample_size: 0,
+        }
+    }
+
+    /// Only checks the given feature indices, e.g. the continuous ones.
+    pub fn with_columns(mut self, columns: Vec<usize>) -> Self {
+        self.columns = Some(columns);
+        self
+    }
+
+    /// Lower and upper fences per feature for the IQR and z-score methods.
+    /// Unchecked features have infinite fences.
+    pub fn fences(&self) -> (&[f32], &[f32]) {
+        (&self.lower, &self.upper)
+    }
+
+    fn checked_columns(&self, num_features: usize) -> Vec<usize> {
+        match &self.columns {
+            Some(columns) => columns.iter().copied().filter(|&i| i < num_features).collect(),
+            None => (0..num_features).collect(),
+        }
+    }
+
+    fn check_fitted(&self, record: &ProcessedPatientRecord) -> Result<()> {
+        if self.num_features == 0 {
+            return Err(PredictorError::NotFitted("OutlierDetector"));
+        }
+        check_width(record, self.num_features)
+    }
+
+    /// Anomaly score of a record under the isolation forest, between 0 and 1.
+    /// Scores near 1 mark records that are isolated in few splits.
+    pub fn anomaly_score(&self, record: &ProcessedPatientRecord) -> Result<f32> {
+        self.check_fitted(record)?;
+        if self.forest.is_empty() {
+            return Err(PredictorError::InvalidHyperparameter(
+                "anomaly scores need the isolation forest method".to_string(),
+            ));
+        }
+        if record.features.iter().any(|value| value.is_nan()) {
+            return Err(PredictorError::InvalidInput(
+                "the isolation forest needs imputed records".to_string(),
+            ));
+        }
+
+        let mean_path = self
+            .forest
+            .iter()
+            .map(|tree| tree.path_length(&record.features, 0))
+            .sum::<f32>()
+            / self.forest.len() as f32;
+        Ok(2f32.powf(-mean_path / average_path_length(self.forest_sample_size).max(1.0)))
+    }
+
+    // Features of the record that fall outside the fences.
+    fn outlying_features(&self, record: &ProcessedPatientRecord) -> Vec<usize> {
+        record
+            .features
+            .iter()
+            .enumerate()
+            .filter(|&(i, &value)| value < self.lower[i] || value > self.upper[i])
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn is_outlier(&self, record: &ProcessedPatientRecord) -> Result<bool> {
+        self.check_fitted(record)?;
+        match self.method {
+            OutlierMethod::IsolationForest { threshold, .. } => Ok(self.anomaly_score(record)? > threshold),
+            _ => Ok(!self.outlying_features(record).is_empty()),
+        }
+    }
+
+    /// Transforms a split and reports which records were outliers.
+    pub fn transform_with_report(
+        &self,
+        data: &[ProcessedPatientRecord],
+    ) -> Result<(Vec<ProcessedPatientRecord>, OutlierReport)> {
+        let mut report = OutlierReport::default();
+        if self.forest.is_empty() {
+            report.outliers_per_feature = vec![0; self.num_features];
+        }
+
+        let mut records = Vec::with_capacity(data.len());
+        for (index, record) in data.iter().enumerate() {
+            self.check_fitted(record)?;
+            let outlier = if self.forest.is_empty() {
+                let features = self.outlying_features(record);
+                for &i in &features {
+                    report.outliers_per_feature[i] += 1;
+                }
+                !features.is_empty()
+            } else {
+                self.is_outlier(record)?
+            };
+
+            if outlier {
+                report.outliers.push(index);
+                if self.action == OutlierAction::Remove {
+                    report.removed_rows += 1;
+                    continue;
+                }
+            }
+            records.push(record.clone());
+        }
+
+        Ok((records, report))
+    }
+}
+
+impl Transformer for OutlierDetector {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            check_width(record, num_features)?;
+        }
+        let columns = self.checked_columns(num_features);
+
+        self.lower = vec![f32::NEG_INFINITY; num_features];
+        self.upper = vec![f32::INFINITY; num_features];
+        self.forest.clear();
+
+        match self.method {
+            OutlierMethod::Iqr { k } => {
+                for &i in &columns {
+                    let mut values: Vec<f32> = data.iter().map(|r| r.features[i]).filter(|v| !v.is_nan()).collect();
+                    if values.is_empty() {
+                        continue;
+                    }
+                    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    let (q1, q3) = (quantile(&values, 0.25), quantile(&values, 0.75));
+                    self.lower[i] = q1 - k * (q3 - q1);
+                    self.upper[i] = q3 + k * (q3 - q1);
+                }
+            }
+            OutlierMethod::ZScore { threshold } => {
+                for &i in &columns {
+                    let values: Vec<f32> = data.iter().map(|r| r.features[i]).filter(|v| !v.is_nan()).collect();
+                    if values.len() < 2 {
+                        continue;
+                    }
+                    let n = values.len() as f32;
+                    let mean = values.iter().sum::<f32>() / n;
+                    let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / (n - 1.0)).sqrt();
+                    self.lower[i] = mean - threshold * std_dev;
+                    self.upper[i] = mean + threshold * std_dev;
+                }
+            }
+            OutlierMethod::IsolationForest { trees, sample_size, .. } => {
+                if trees == 0 || sample_size < 2 {
+                    return Err(PredictorError::InvalidHyperparameter(format!(
+                        "isolation forest needs at least 1 tree and a sample size of 2, got {} and {}",
+                        trees, sample_size
+                    )));
+                }
+                if data.iter().any(|record| record.features.iter().any(|value| value.is_nan())) {
+                    return Err(PredictorError::InvalidInput(
+                        "the isolation forest needs imputed records".to_string(),
+                    ));
+                }
+
+                let sample_size = sample_size.min(data.len());
+                let max_depth = (sample_size as f32).log2().ceil() as usize;
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                for _ in 0..trees {
+                    let samples: Vec<&[f32]> = sample(&mut rng, data.len(), sample_size)
+                        .iter()
+                        .map(|i| data[i].features.as_slice())
+                        .collect();
+                    self.forest.push(IsolationNode::grow(&samples, &columns, 0, max_depth, &mut rng));
+                }
+                self.forest_sample_size = sample_size;
+            }
+        }
+
+        self.num_features = num_features;
+        Ok(())
+    }
+
+    /// Returns the record unchanged; outliers are only removed from whole splits.
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.check_fitted(record)?;
+        Ok(record.clone())
+    }
+
+    fn transform(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        Ok(self.transform_with_report(data)?.0)
+    }
+}
@@ -0,0 +1,266 @@
+use ndarray::{Array1, Array2, Axis};
+
+use super::{ProcessedPatientRecord, Transformer, check_width};
+use crate::error::{PredictorError, Result};
+
+/// How many principal components a `PCA` keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Components {
+    /// Exactly this many, capped at the number of features.
+    Count(usize),
+    /// The fewest components whose explained variance ratios sum to at least this fraction.
+    VarianceRatio(f32),
+}
+
+/// Principal component analysis. `fit` centres the training split and finds
+/// the eigenvectors of its covariance matrix with the Jacobi method;
+/// `transform_record` projects a record onto the leading components.
+/// Features are not scaled, so put a `StandardScaler` before it when the
+/// features have different units. Records must not have missing values.
+#[derive(Debug, Clone)]
+pub struct PCA {
+    components: Components,
+    mean: Array1<f64>,
+    // One component per row
+    basis: Array2<f64>,
+    explained_variance: Vec<f32>,
+    explained_variance_ratio: Vec<f32>,
+}
+
+const MAX_SWEEPS: usize = 100;
+
+// Eigenvalues and eigenvectors (as columns) of a symmetric matrix by cyclic
+// Jacobi rotations.
+fn symmetric_eigen(matrix: &Array2<f64>) -> (Vec<f64>, Array2<f64>) {
+    let n = matrix.nrows();
+    let mut a = matrix.clone();
+    let mut vectors = Array2::<f64>::eye(n);
+
+    for _ in 0..MAX_SWEEPS {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[[i, j]] * a[[i, j]])
+            .sum();
+        if off_diagonal < 1e-18 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                if a[[p, q]].abs() < 1e-300 {
+                    continue;
+                }
+                // Rotation angle that zeroes a[p][q]
+                let theta = (a[[q, q]] - a[[p, p]]) / (2.0 * a[[p, q]]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                    a[[k, p]] = c * akp - s * akq;
+                    a[[k, q]] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = c * apk - s * aqk;
+                    a[[q, k]] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (vectors[[k, p]], vectors[[k, q]]);
+                    vectors[[k, p]] = c * vkp - s * vkq;
+                    vectors[[k, q]] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    ((0..n).map(|i| a[[i, i]]).collect(), vectors)
+}
+
+impl PCA {
+    pub fn new(components: Components) -> Self {
+        PCA {
+            components,
+            mean: Array1::zeros(0),
+            basis: Array2::zeros((0, 0)),
+            explained_variance: Vec::new(),
+            explained_variance_ratio: Vec::new(),
+        }
+    }
+
+    /// Variance of the training data along each kept component.
+    pub fn explained_variance(&self) -> &[f32] {
+        &self.explained_variance
+    }
+
+    /// Share of the total training variance along each kept component.
+    pub fn explained_variance_ratio(&self) -> &[f32] {
+        &self.explained_variance_ratio
+    }
+
+    /// The kept components, one per row, in the original feature space.
+    pub fn components(&self) -> &Array2<f64> {
+        &self.basis
+    }
+
+    pub fn n_components(&self) -> usize {
+        self.basis.nrows()
+    }
+
+    fn check_fitted(&self, record: &ProcessedPatientRecord, width: usize) -> Result<()> {
+        if self.mean.is_empty() {
+            return Err(PredictorError::NotFitted("PCA"));
+        }
+        check_width(record, width)?;
+        if record.features.iter().any(|value| value.is_nan()) {
+            return Err(PredictorError::InvalidInput("PCA needs imputed records".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Maps a record of component scores back to the original feature space.
+    /// Variance along the dropped components is lost.
+    pub fn inverse_transform(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.check_fitted(record, self.n_components())?;
+        let scores = Array1::from_iter(record.features.iter().map(|&value| value as f64));
+        let restored = scores.dot(&self.basis) + &self.mean;
+        Ok(ProcessedPatientRecord {
+            features: restored.iter().map(|&value| value as f32).collect(),
+            target: record.target,
+            site: record.site.clone(),
+        })
+    }
+}
+
+impl Transformer for PCA {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            check_width(record, num_features)?;
+            if record.features.iter().any(|value| value.is_nan()) {
+                return Err(PredictorError::InvalidInput("PCA needs imputed records".to_string()));
+            }
+        }
+        match self.components {
+            Components::Count(0) => {
+                return Err(PredictorError::InvalidHyperparameter(
+                    "PCA needs at least 1 component".to_string(),
+                ));
+            }
+            Components::VarianceRatio(ratio) if !(ratio > 0.0 && ratio <= 1.0) => {
+                return Err(PredictorError::InvalidHyperparameter(format!(
+                    "explained variance ratio must be in (0, 1], got {}",
+                    ratio
+                )));
+            }
+            _ => {}
+        }
+
+        let x = Array2::from_shape_fn((data.len(), num_features), |(i, j)| data[i].features[j] as f64);
+        let mean = x.mean_axis(Axis(0)).expect("data is not empty");
+        let centred = &x - &mean;
+        let covariance = centred.t().dot(&centred) / (data.len().max(2) - 1) as f64;
+
+        let (values, vectors) = symmetric_eigen(&covariance);
+        let mut order: Vec<usize> = (0..num_features).collect();
+        order.sort_by(|&a, &b| values[b].total_cmp(&values[a]).then(a.cmp(&b)));
+
+        // Rounding can leave tiny negative eigenvalues
+        let variances: Vec<f64> = order.iter().map(|&i| values[i].max(0.0)).collect();
+        let total: f64 = variances.iter().sum();
+        let ratios: Vec<f64> = variances
+            .iter()
+            .map(|v| if total > 0.0 { v / total } else { 0.0 })
+            .collect();
+
+        let count = match self.components {
+            Components::Count(count) => count.min(num_features),
+            Components::VarianceRatio(target) => {
+                let mut cumulative = 0.0;
+                ratios
+                    .iter()
+                    .position(|ratio| {
+                        cumulative += ratio;
+                        cumulative >= target as f64 - 1e-9
+                    })
+                    .map_or(num_features, |i| i + 1)
+            }
+        };
+
+        let mut basis = Array2::zeros((count, num_features));
+        for (row, &i) in order.iter().take(count).enumerate() {
+            let mut vector = vectors.column(i).to_owned();
+            // Make the largest loading positive so the signs are reproducible
+            let largest = vector.iter().copied().fold(0.0, |best: f64, v| if v.abs() > best.abs() { v } else { best });
+            if largest < 0.0 {
+                vector.mapv_inplace(|v| -v);
+            }
+            basis.row_mut(row).assign(&vector);
+        }
+
+        self.mean = mean;
+        self.basis = basis;
+        self.explained_variance = variances.iter().take(count).map(|&v| v as f32).collect();
+        self.explained_variance_ratio = ratios.iter().take(count).map(|&r| r as f32).collect();
+        Ok(())
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.check_fitted(record, self.mean.len())?;
+        let x = Array1::from_iter(record.features.iter().map(|&value| value as f64)) - &self.mean;
+        let scores = self.basis.dot(&x);
+        Ok(ProcessedPatientRecord {
+            features: scores.iter().map(|&value| value as f32).collect(),
+            target: record.target,
+            site: record.site.clone(),
+        })
+    }
+
+    /// Components are named PC1, PC2, … once fitted.
+    fn feature_names(&self, input: &[String]) -> Vec<String> {
+        if self.mean.is_empty() {
+            return input.to_vec();
+        }
+        (1..=self.n_components()).map(|i| format!("PC{}", i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_eigen_of_a_known_matrix() {
+        // Eigenvalues 1 and 3, with eigenvectors along (1, -1) and (1, 1)
+        let matrix = Array2::from_shape_vec((2, 2), vec![2.0, 1.0, 1.0, 2.0]).unwrap();
+        let (values, vectors) = symmetric_eigen(&matrix);
+        let mut sorted = values.clone();
+        sorted.sort_by(f64::total_cmp);
+        assert!((sorted[0] - 1.0).abs() < 1e-12 && (sorted[1] - 3.0).abs() < 1e-12);
+
+        for (i, &value) in values.iter().enumerate() {
+            let vector = vectors.column(i);
+            let product = matrix.dot(&vector);
+            for j in 0..2 {
+                assert!((product[j] - value * vector[j]).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn symmetric_eigen_reconstructs_a_3x3_matrix() {
+        let matrix = Array2::from_shape_vec((3, 3), vec![4.0, 1.0, 2.0, 1.0, 3.0, 0.5, 2.0, 0.5, 5.0]).unwrap();
+        let (values, vectors) = symmetric_eigen(&matrix);
+        // V diag(λ) Vᵀ gives back the matrix, and V is orthonormal
+        let restored = vectors.dot(&Array2::from_diag(&Array1::from(values.clone()))).dot(&vectors.t());
+        let identity = vectors.t().dot(&vectors);
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!((restored[[i, j]] - matrix[[i, j]]).abs() < 1e-10);
+                assert!((identity[[i, j]] - if i == j { 1.0 } else { 0.0 }).abs() < 1e-10);
+            }
+        }
+        assert!((values.iter().sum::<f64>() - 12.0).abs() < 1e-10);
+    }
+}
@@ -0,0 +1,212 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+
+use super::{ProcessedPatientRecord, Transformer, check_width};
+use crate::error::{PredictorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResamplingStrategy {
+    /// Duplicate random records of every class up to the size of the largest class.
+    RandomOversample,
+    /// Drop random records of every class down to the size of the smallest class.
+    RandomUndersample,
+    /// Grow every class to the size of the largest one with synthetic records,
+    /// each interpolated between a record and one of its `k` nearest
+    /// neighbours of the same class. A class with a single record has no
+    /// neighbours, so that record is duplicated as in `RandomOversample`.
+    Smote { k: usize },
+    /// Remove the record of the larger class from every Tomek link: a pair of
+    /// records of different classes that are each other's nearest neighbour.
+    TomekLinks,
+}
+
+/// Rebalances the classes of a training split. Inside a `Pipeline` only
+/// `fit_transform`, which training uses, resamples; `transform` and
+/// `transform_record` pass records through, so validation, test and
+/// inference records are never resampled. SMOTE and Tomek links need
+/// imputed records, and SMOTE makes categorical codes fractional, so use it
+/// after one-hot encoding.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    strategy: ResamplingStrategy,
+    seed: u64,
+}
+
+fn class_groups(data: &[ProcessedPatientRecord]) -> BTreeMap<u8, Vec<usize>> {
+    let mut groups: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    for (i, record) in data.iter().enumerate() {
+        groups.entry(record.target).or_default().push(i);
+    }
+    groups
+}
+
+fn squared_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum()
+}
+
+// Nearest record to `data[i]` among `candidates`, excluding itself; ties go
+// to the earlier record.
+fn nearest(data: &[ProcessedPatientRecord], i: usize, candidates: impl Iterator<Item = usize>) -> Option<usize> {
+    candidates
+        .filter(|&j| j != i)
+        .map(|j| (j, squared_distance(&data[i].features, &data[j].features)))
+        .fold(None, |best: Option<(usize, f32)>, (j, d)| match best {
+            Some((_, best_d)) if best_d <= d => best,
+            _ => Some((j, d)),
+        })
+        .map(|(j, _)| j)
+}
+
+impl Resampler {
+    /// `seed` drives which records are drawn, dropped or interpolated.
+    pub fn new(strategy: ResamplingStrategy, seed: u64) -> Self {
+        Resampler { strategy, seed }
+    }
+
+    /// Resamples a training split. The result keeps the original records in
+    /// order, followed by any added ones.
+    pub fn resample(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            check_width(record, num_features)?;
+        }
+        let needs_distances = matches!(self.strategy, ResamplingStrategy::Smote { .. } | ResamplingStrategy::TomekLinks);
+        if needs_distances && data.iter().any(|record| record.features.iter().any(|value| value.is_nan())) {
+            return Err(PredictorError::InvalidInput(
+                "SMOTE and Tomek links need imputed records".to_string(),
+            ));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let groups = class_groups(data);
+        let largest = groups.values().map(Vec::len).max().unwrap_or(0);
+        let smallest = groups.values().map(Vec::len).min().unwrap_or(0);
+
+        match self.strategy {
+            ResamplingStrategy::RandomOversample => {
+                let mut records = data.to_vec();
+                for indices in groups.values() {
+                    for _ in indices.len()..largest {
+                        let &i = indices.choose(&mut rng).expect("classes are not empty");
+                        records.push(data[i].clone());
+                    }
+                }
+                Ok(records)
+            }
+            ResamplingStrategy::RandomUndersample => {
+                let mut keep = vec![false; data.len()];
+                for indices in groups.values() {
+                    for &i in indices.choose_multiple(&mut rng, smallest) {
+                        keep[i] = true;
+                    }
+                }
+                Ok(data
+                    .iter()
+                    .zip(keep)
+                    .filter(|(_, keep)| *keep)
+                    .map(|(record, _)| record.clone())
+                    .collect())
+            }
+            ResamplingStrategy::Smote { k } => {
+                if k == 0 {
+                    return Err(PredictorError::InvalidHyperparameter("SMOTE needs k of at least 1".to_string()));
+                }
+                let mut records = data.to_vec();
+                for indices in groups.values() {
+                    if indices.len() == largest {
+                        continue;
+                    }
+                    // A single record has no neighbour to interpolate towards
+                    if indices.len() == 1 {
+                        records.extend((1..largest).map(|_| data[indices[0]].clone()));
+                        continue;
+                    }
+                    // The k nearest neighbours of each record within its class
+                    let neighbours: Vec<Vec<usize>> = indices
+                        .iter()
+                        .map(|&i| {
+                            let mut others: Vec<(usize, f32)> = indices
+                                .iter()
+                                .filter(|&&j| j != i)
+                                .map(|&j| (j, squared_distance(&data[i].features, &data[j].features)))
+                                .collect();
+                            others.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+                            others.into_iter().take(k).map(|(j, _)| j).collect()
+                        })
+                        .collect();
+
+                    for _ in indices.len()..largest {
+                        let base = rng.gen_range(0..indices.len());
+                        let &neighbour = neighbours[base].choose(&mut rng).expect("a class of two has a neighbour");
+                        let (a, b) = (&data[indices[base]], &data[neighbour]);
+                        let gap: f32 = rng.gen_range(0.0..1.0);
+                        records.push(ProcessedPatientRecord {
+                            features: a.features.iter().zip(&b.features).map(|(x, y)| x + gap * (y - x)).collect(),
+                            target: a.target,
+                            site: a.site.clone(),
+                        });
+                    }
+                }
+                Ok(records)
+            }
+            ResamplingStrategy::TomekLinks => {
+                let nearest: Vec<Option<usize>> = (0..data.len()).map(|i| nearest(data, i, 0..data.len())).collect();
+                let mut remove = vec![false; data.len()];
+                for (i, &j) in nearest.iter().enumerate() {
+                    let Some(j) = j else { continue };
+                    if i < j && nearest[j] == Some(i) && data[i].target != data[j].target {
+                        let (size_i, size_j) = (groups[&data[i].target].len(), groups[&data[j].target].len());
+                        remove[if size_i >= size_j { i } else { j }] = true;
+                    }
+                }
+                Ok(data
+                    .iter()
+                    .zip(remove)
+                    .filter(|(_, remove)| !*remove)
+                    .map(|(record, _)| record.clone())
+                    .collect())
+            }
+        }
+    }
+}
+
+impl Transformer for Resampler {
+    /// Nothing is learned; resampling happens in `fit_transform`.
+    fn fit(&mut self, _data: &[ProcessedPatientRecord]) -> Result<()> {
+        Ok(())
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        Ok(record.clone())
+    }
+
+    fn transform(&self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        Ok(data.to_vec())
+    }
+
+    fn fit_transform(&mut self, data: &[ProcessedPatientRecord]) -> Result<Vec<ProcessedPatientRecord>> {
+        self.resample(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smote_grows_a_single_record_class() {
+        let data: Vec<ProcessedPatientRecord> = (0..5)
+            .map(|i| ProcessedPatientRecord {
+                features: vec![i as f32],
+                target: if i == 0 { 1 } else { 0 },
+                site: None,
+            })
+            .collect();
+        let resampled = Resampler::new(ResamplingStrategy::Smote { k: 3 }, 0).resample(&data).unwrap();
+        let minority: Vec<&ProcessedPatientRecord> = resampled.iter().filter(|r| r.target == 1).collect();
+        assert_eq!(minority.len(), 4);
+        assert!(minority.iter().all(|r| r.features == vec![0.0]));
+    }
+}
@@ -0,0 +1,220 @@
+use super::{ProcessedPatientRecord, Transformer, check_width};
+use crate::error::{PredictorError, Result};
+
+// Per-feature affine parameters shared by the scalers: x' = (x - offset) / scale.
+// Columns outside `columns` keep offset 0 and scale 1.
+#[derive(Debug, Clone, Default)]
+struct ScalingParams {
+    columns: Option<Vec<usize>>,
+    offsets: Vec<f32>,
+    scales: Vec<f32>,
+}
+
+impl ScalingParams {
+    fn fit<F>(&mut self, data: &[ProcessedPatientRecord], mut offset_and_scale: F) -> Result<()>
+    where
+        F: FnMut(&mut [f32]) -> (f32, f32),
+    {
+        let num_features = data.first().ok_or(PredictorError::EmptyDataset)?.features.len();
+        for record in data {
+            check_width(record, num_features)?;
+        }
+        self.offsets = vec![0.0; num_features];
+        self.scales = vec![1.0; num_features];
+
+        for i in 0..num_features {
+            if let Some(columns) = &self.columns
+                && !columns.contains(&i)
+            {
+                continue;
+            }
+
+            let mut values: Vec<f32> = data
+                .iter()
+                .map(|record| record.features[i])
+                .filter(|value| !value.is_nan())
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+
+            let (offset, scale) = offset_and_scale(&mut values);
+            self.offsets[i] = offset;
+            // Constant features are only centred
+            self.scales[i] = if scale > f32::EPSILON { scale } else { 1.0 };
+        }
+        Ok(())
+    }
+
+    fn check_fitted(&self, record: &ProcessedPatientRecord, component: &'static str) -> Result<()> {
+        if self.offsets.is_empty() {
+            return Err(PredictorError::NotFitted(component));
+        }
+        check_width(record, self.offsets.len())
+    }
+
+    fn apply(&self, record: &ProcessedPatientRecord, component: &'static str) -> Result<ProcessedPatientRecord> {
+        self.check_fitted(record, component)?;
+        let mut record = record.clone();
+        for ((value, offset), scale) in record.features.iter_mut().zip(&self.offsets).zip(&self.scales) {
+            *value = (*value - offset) / scale;
+        }
+        Ok(record)
+    }
+
+    fn invert(&self, record: &ProcessedPatientRecord, component: &'static str) -> Result<ProcessedPatientRecord> {
+        self.check_fitted(record, component)?;
+        let mut record = record.clone();
+        for ((value, offset), scale) in record.features.iter_mut().zip(&self.offsets).zip(&self.scales) {
+            *value = *value * scale + offset;
+        }
+        Ok(record)
+    }
+}
+
+fn sorted(values: &mut [f32]) -> &[f32] {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    values
+}
+
+// Linear-interpolated quantile of sorted values.
+pub(crate) fn quantile(sorted: &[f32], q: f32) -> f32 {
+    let position = q * (sorted.len() - 1) as f32;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f32)
+}
+
+/// Centres each feature on its training mean and divides by its standard deviation.
+#[derive(Debug, Clone, Default)]
+pub struct StandardScaler {
+    params: ScalingParams,
+}
+
+impl StandardScaler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts scaling to the given feature columns, e.g. to leave one-hot indicators untouched.
+    pub fn with_columns(mut self, columns: Vec<usize>) -> Self {
+        self.params.columns = Some(columns);
+        self
+    }
+
+    pub fn means(&self) -> &[f32] {
+        &self.params.offsets
+    }
+
+    pub fn std_devs(&self) -> &[f32] {
+        &self.params.scales
+    }
+
+    pub fn inverse_transform(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.invert(record, "StandardScaler")
+    }
+}
+
+impl Transformer for StandardScaler {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        self.params.fit(data, |values| {
+            let n = values.len() as f32;
+            let mean = values.iter().sum::<f32>() / n;
+            let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / n;
+            (mean, variance.sqrt())
+        })
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.apply(record, "StandardScaler")
+    }
+}
+
+/// Rescales each feature so its training values span `[0, 1]`.
+#[derive(Debug, Clone, Default)]
+pub struct MinMaxScaler {
+    params: ScalingParams,
+}
+
+impl MinMaxScaler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts scaling to the given feature columns.
+    pub fn with_columns(mut self, columns: Vec<usize>) -> Self {
+        self.params.columns = Some(columns);
+        self
+    }
+
+    pub fn mins(&self) -> &[f32] {
+        &self.params.offsets
+    }
+
+    pub fn ranges(&self) -> &[f32] {
+        &self.params.scales
+    }
+
+    pub fn inverse_transform(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.invert(record, "MinMaxScaler")
+    }
+}
+
+impl Transformer for MinMaxScaler {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        self.params.fit(data, |values| {
+            let values = sorted(values);
+            let min = values[0];
+            (min, values[values.len() - 1] - min)
+        })
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.apply(record, "MinMaxScaler")
+    }
+}
+
+/// Centres each feature on its training median and divides by its interquartile
+/// range, so a few extreme values do not dominate the scale.
+#[derive(Debug, Clone, Default)]
+pub struct RobustScaler {
+    params: ScalingParams,
+}
+
+impl RobustScaler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts scaling to the given feature columns.
+    pub fn with_columns(mut self, columns: Vec<usize>) -> Self {
+        self.params.columns = Some(columns);
+        self
+    }
+
+    pub fn medians(&self) -> &[f32] {
+        &self.params.offsets
+    }
+
+    pub fn iqrs(&self) -> &[f32] {
+        &self.params.scales
+    }
+
+    pub fn inverse_transform(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.invert(record, "RobustScaler")
+    }
+}
+
+impl Transformer for RobustScaler {
+    fn fit(&mut self, data: &[ProcessedPatientRecord]) -> Result<()> {
+        self.params.fit(data, |values| {
+            let values = sorted(values);
+            let median = quantile(values, 0.5);
+            (median, quantile(values, 0.75) - quantile(values, 0.25))
+        })
+    }
+
+    fn transform_record(&self, record: &ProcessedPatientRecord) -> Result<ProcessedPatientRecord> {
+        self.params.apply(record, "RobustScaler")
+    }
+}
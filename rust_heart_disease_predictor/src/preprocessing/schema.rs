@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+
+use crate::error::{PredictorError, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureKind {
+    Continuous,
+    Categorical,
+    Ordinal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    pub name: String,
+    pub kind: FeatureKind,
+    /// Optional labels for a categorical or ordinal column whose values are
+    /// text; each label is encoded as its position in this list.
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Read a value of exactly zero as missing, for files that code an
+    /// unmeasured value as 0 where 0 is not a possible measurement.
+    #[serde(default)]
+    pub zero_is_missing: bool,
+}
+
+/// Describes the layout of a delimited data file: every column in file order
+/// (including the target), which column is the target, how missing values are
+/// written, whether the first row is a header and whether the target is
+/// collapsed to two classes.
+///
+/// A schema can be built in code or read from a JSON file such as:
+///
+/// ```json
+/// {
+///   "columns": [
+///     { "name": "age", "kind": "continuous" },
+///     { "name": "cp", "kind": "categorical" },
+///     { "name": "num", "kind": "ordinal" }
+///   ],
+///   "target": "num",
+///   "missing_token": "?",
+///   "has_header": false,
+///   "binarize_target": true
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetSchema {
+    pub columns: Vec<ColumnSpec>,
+    pub target: String,
+    #[serde(default = "default_missing_token")]
+    pub missing_token: String,
+    #[serde(default)]
+    pub has_header: bool,
+    /// Map every positive target value to class 1. When false, target values
+    /// are kept as class labels, e.g. the 0–4 severity of the UCI `num` column.
+    #[serde(default = "default_binarize_target")]
+    pub binarize_target: bool,
+}
+
+fn default_missing_token() -> String {
+    "?".to_string()
+}
+
+fn default_binarize_target() -> bool {
+    true
+}
+
+impl DatasetSchema {
+    pub fn new(target: &str) -> Self {
+        DatasetSchema {
+            columns: Vec::new(),
+            target: target.to_string(),
+            missing_token: default_missing_token(),
+            has_header: false,
+            binarize_target: default_binarize_target(),
+        }
+    }
+
+    pub fn with_column(mut self, name: &str, kind: FeatureKind) -> Self {
+        self.columns.push(ColumnSpec {
+            name: name.to_string(),
+            kind,
+            categories: Vec::new(),
+            zero_is_missing: false,
+        });
+        self
+    }
+
+    /// Reads zeros in the named column as missing values.
+    ///
+    /// # Panics
+    ///
+    /// If no column added so far is named `name`, since a misspelt name would
+    /// otherwise leave the zeros silently in place.
+    pub fn with_zero_as_missing(mut self, name: &str) -> Self {
+        match self.columns.iter_mut().find(|column| column.name == name) {
+            Some(column) => column.zero_is_missing = true,
+            None => panic!("cannot read zeros as missing in undeclared column '{}'", name),
+        }
+        self
+    }
+
+    pub fn with_missing_token(mut self, token: &str) -> Self {
+        self.missing_token = token.to_string();
+        self
+    }
+
+    pub fn with_header(mut self, has_header: bool) -> Self {
+        self.has_header = has_header;
+        self
+    }
+
+    pub fn with_binary_target(mut self, binarize: bool) -> Self {
+        self.binarize_target = binarize;
+        self
+    }
+
+    /// The 14-column layout shared by the processed UCI heart-disease files.
+    /// The Switzerland and VA files code unmeasured `chol` and `trestbps` as
+    /// 0, so zeros in those columns are read as missing.
+    pub fn uci_heart() -> Self {
+        use FeatureKind::*;
+
+        DatasetSchema::new("num")
+            .with_column("age", Continuous)
+            .with_column("sex", Categorical)
+            .with_column("cp", Categorical)
+            .with_column("trestbps", Continuous)
+            .with_column("chol", Continuous)
+            .with_column("fbs", Categorical)
+            .with_column("restecg", Categorical)
+            .with_column("thalach", Continuous)
+            .with_column("exang", Categorical)
+            .with_column("oldpeak", Continuous)
+            .with_column("slope", Categorical)
+            .with_column("ca", Ordinal)
+            .with_column("thal", Categorical)
+            .with_column("num", Ordinal)
+            .with_zero_as_missing("trestbps")
+            .with_zero_as_missing("chol")
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let schema: DatasetSchema = serde_json::from_reader(reader)?;
+        schema.target_index()?;
+        Ok(schema)
+    }
+
+    /// Position of the target column among `columns`.
+    pub fn target_index(&self) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name == self.target)
+            .ok_or_else(|| {
+                PredictorError::SchemaMismatch(format!("target column '{}' is not declared in the schema", self.target))
+            })
+    }
+
+    /// The non-target columns, in the order their values appear in `ProcessedPatientRecord.features`.
+    pub fn feature_columns(&self) -> impl Iterator<Item = &ColumnSpec> {
+        self.columns.iter().filter(move |column| column.name != self.target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "undeclared column 'cholesterol'")]
+    fn zero_as_missing_rejects_an_unknown_column() {
+        let _ = DatasetSchema::new("num")
+            .with_column("chol", FeatureKind::Continuous)
+            .with_zero_as_missing("cholesterol");
+    }
+}
@@ -0,0 +1,157 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use crate::error::{PredictorError, Result};
+
+use super::ProcessedPatientRecord;
+
+/// Record indices of each part of a split. `validation` is empty for a plain
+/// train/test split. The indices can be saved to JSON so a split can be reused.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitIndices {
+    pub train: Vec<usize>,
+    pub validation: Vec<usize>,
+    pub test: Vec<usize>,
+}
+
+impl SplitIndices {
+    pub fn save(&self, path: &str) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+/// Copies the records at `indices`, in that order.
+pub fn select(data: &[ProcessedPatientRecord], indices: &[usize]) -> Vec<ProcessedPatientRecord> {
+    indices.iter().map(|&i| data[i].clone()).collect()
+}
+
+/// Groups record indices by target class, or into a single group when not stratifying.
+fn class_groups(data: &[ProcessedPatientRecord], stratify: bool) -> Vec<Vec<usize>> {
+    if !stratify {
+        return vec![(0..data.len()).collect()];
+    }
+    let mut groups: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    for (i, record) in data.iter().enumerate() {
+        groups.entry(record.target).or_default().push(i);
+    }
+    groups.into_values().collect()
+}
+
+/// Splits into train and test sets, keeping the class ratio of `target` in both.
+pub fn stratified_split<R: Rng + ?Sized>(
+    data: &[ProcessedPatientRecord],
+    test_size: f32,
+    rng: &mut R,
+) -> SplitIndices {
+    train_validation_test_split(data, 0.0, test_size, true, rng)
+}
+
+/// Splits into train, validation and test sets. `validation_size` and
+/// `test_size` are fractions of the whole dataset. When `stratify` is set,
+/// each class is split separately so every part keeps the class ratio.
+pub fn train_validation_test_split<R: Rng + ?Sized>(
+    data: &[ProcessedPatientRecord],
+    validation_size: f32,
+    test_size: f32,
+    stratify: bool,
+    rng: &mut R,
+) -> SplitIndices {
+    let mut split = SplitIndices::default();
+
+    for mut group in class_groups(data, stratify) {
+        group.shuffle(rng);
+        let test_count = ((group.len() as f32 * test_size).round() as usize).min(group.len());
+        let validation_count = ((group.len() as f32 * validation_size).round() as usize).min(group.len() - test_count);
+
+        split.test.extend_from_slice(&group[..test_count]);
+        split.validation.extend_from_slice(&group[test_count..test_count + validation_count]);
+        split.train.extend_from_slice(&group[test_count + validation_count..]);
+    }
+
+    // Interleave the classes so models trained in data order see a mix
+    split.train.shuffle(rng);
+    split.validation.shuffle(rng);
+    split.test.shuffle(rng);
+    split
+}
+
+/// Partitions the records into `k` folds and returns one split per fold, with
+/// that fold as `test` and the rest as `train`. When `stratify` is set, each
+/// class is dealt across the folds separately so every fold keeps the class ratio.
+pub fn k_fold<R: Rng + ?Sized>(
+    data: &[ProcessedPatientRecord],
+    k: usize,
+    stratify: bool,
+    rng: &mut R,
+) -> Result<Vec<SplitIndices>> {
+    if k < 2 {
+        return Err(PredictorError::InvalidHyperparameter(format!("cross-validation needs at least 2 folds, got {}", k)));
+    }
+    let mut folds: Vec<Vec<usize>> = vec![Vec::new(); k];
+    let mut next_fold = 0;
+    for mut group in class_groups(data, stratify) {
+        group.shuffle(rng);
+        // Continue dealing where the previous class stopped so fold sizes stay balanced
+        for index in group {
+            folds[next_fold].push(index);
+            next_fold = (next_fold + 1) % k;
+        }
+    }
+
+    Ok((0..k)
+        .map(|test_fold| {
+            let mut train: Vec<usize> = folds
+                .iter()
+                .enumerate()
+                .filter(|(fold, _)| *fold != test_fold)
+                .flat_map(|(_, indices)| indices.iter().copied())
+                .collect();
+            train.shuffle(rng);
+            SplitIndices {
+                train,
+                validation: Vec::new(),
+                test: folds[test_fold].clone(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn k_fold_needs_two_folds() {
+        let data: Vec<ProcessedPatientRecord> = (0..6)
+            .map(|i| ProcessedPatientRecord {
+                features: vec![i as f32],
+                target: (i % 2) as u8,
+                site: None,
+            })
+            .collect();
+        let mut rng = StdRng::seed_from_u64(0);
+        for k in [0, 1] {
+            assert!(matches!(
+                k_fold(&data, k, true, &mut rng),
+                Err(PredictorError::InvalidHyperparameter(_))
+            ));
+        }
+
+        let folds = k_fold(&data, 2, true, &mut rng).unwrap();
+        assert_eq!(folds.len(), 2);
+        assert!(folds.iter().all(|fold| fold.train.len() == 3 && fold.test.len() == 3));
+    }
+}
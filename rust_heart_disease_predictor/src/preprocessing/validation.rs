@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::{Dataset, ProcessedPatientRecord, check_width};
+use crate::error::{PredictorError, Result};
+
+/// What to do with a record that breaks a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationAction {
+    /// Remove the record.
+    Reject,
+    /// Move out-of-range values to the nearest bound. Disallowed codes cannot
+    /// be clipped, so they become missing values for an `Imputer` to fill.
+    Clip,
+    /// Keep the record unchanged and only report it.
+    Flag,
+}
+
+/// A plausibility rule on one feature. Missing values are never checked.
+#[derive(Debug, Clone)]
+pub enum Rule {
+    Range { feature: String, min: f32, max: f32 },
+    AllowedCodes { feature: String, codes: Vec<f32> },
+    /// `feature` must lie within the range `bounds` returns for the value of
+    /// `given`, e.g. a maximum heart rate that depends on age.
+    Conditional {
+        feature: String,
+        given: String,
+        bounds: fn(f32) -> (f32, f32),
+    },
+}
+
+impl Rule {
+    pub fn range(feature: &str, min: f32, max: f32) -> Self {
+        Rule::Range {
+            feature: feature.to_string(),
+            min,
+            max,
+        }
+    }
+
+    pub fn codes(feature: &str, codes: &[f32]) -> Self {
+        Rule::AllowedCodes {
+            feature: feature.to_string(),
+            codes: codes.to_vec(),
+        }
+    }
+
+    pub fn conditional(feature: &str, given: &str, bounds: fn(f32) -> (f32, f32)) -> Self {
+        Rule::Conditional {
+            feature: feature.to_string(),
+            given: given.to_string(),
+            bounds,
+        }
+    }
+
+    pub fn feature(&self) -> &str {
+        match self {
+            Rule::Range { feature, .. } | Rule::AllowedCodes { feature, .. } | Rule::Conditional { feature, .. } => feature,
+        }
+    }
+}
+
+/// One broken rule: the feature, its original value and what was expected.
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub feature: String,
+    pub value: f32,
+    pub expected: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {} (expected {})", self.feature, self.value, self.expected)
+    }
+}
+
+/// The violations found in one record. `index` is the record's position in the
+/// input, before any rejected rows were removed.
+#[derive(Debug, Clone)]
+pub struct RecordReport {
+    pub index: usize,
+    pub site: Option<String>,
+    pub violations: Vec<Violation>,
+}
+
+/// Every record that broke at least one rule, and the action taken.
+#[derive(Debug, Clone)]
+pub struct ValidationReport {
+    pub action: ValidationAction,
+    pub records_checked: usize,
+    pub records: Vec<RecordReport>,
+}
+
+impl ValidationReport {
+    pub fn is_clean(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Records removed, which is every offending record under `Reject`.
+    pub fn rejected_rows(&self) -> usize {
+        match self.action {
+            ValidationAction::Reject => self.records.len(),
+            _ => 0,
+        }
+    }
+
+    pub fn violations_per_feature(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for violation in self.records.iter().flat_map(|record| &record.violations) {
+            *counts.entry(violation.feature.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+/// Checks records against clinical plausibility rules before they reach a
+/// model. Rules refer to features by name and are checked in order, so under
+/// `Clip` a rule sees the values earlier rules have clipped.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    rules: Vec<Rule>,
+    action: ValidationAction,
+}
+
+impl Validator {
+    pub fn new(action: ValidationAction) -> Self {
+        Validator {
+            rules: Vec::new(),
+            action,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Ranges and codes for the UCI heart-disease features, plus a maximum
+    /// heart rate no more than 40 bpm above the age-predicted 220 − age.
+    pub fn uci_heart(action: ValidationAction) -> Self {
+        Validator::new(action)
+            .with_rule(Rule::range("age", 18.0, 100.0))
+            .with_rule(Rule::codes("sex", &[0.0, 1.0]))
+            .with_rule(Rule::codes("cp", &[1.0, 2.0, 3.0, 4.0]))
+            .with_rule(Rule::range("trestbps", 70.0, 250.0))
+            .with_rule(Rule::range("chol", 80.0, 700.0))
+            .with_rule(Rule::codes("fbs", &[0.0, 1.0]))
+            .with_rule(Rule::codes("restecg", &[0.0, 1.0, 2.0]))
+            .with_rule(Rule::range("thalach", 50.0, 220.0))
+            .with_rule(Rule::codes("exang", &[0.0, 1.0]))
+            .with_rule(Rule::range("oldpeak", 0.0, 10.0))
+            .with_rule(Rule::codes("slope", &[1.0, 2.0, 3.0]))
+            .with_rule(Rule::codes("ca", &[0.0, 1.0, 2.0, 3.0]))
+            .with_rule(Rule::codes("thal", &[3.0, 6.0, 7.0]))
+            .with_rule(Rule::conditional("thalach", "age", |age| (50.0, 260.0 - age)))
+    }
+
+    /// Validates every record of a dataset and applies the action. Fails if a
+    /// rule names a feature the dataset does not have.
+    pub fn validate(&self, dataset: &Dataset) -> Result<(Dataset, ValidationReport)> {
+        let mut records = Vec::with_capacity(dataset.len());
+        let mut report = ValidationReport {
+            action: self.action,
+            records_checked: dataset.len(),
+            records: Vec::new(),
+        };
+
+        for (index, record) in dataset.records.iter().enumerate() {
+            let (checked, violations) = self.validate_record(&dataset.feature_names, record)?;
+            let rejected = !violations.is_empty() && self.action == ValidationAction::Reject;
+            if !violations.is_empty() {
+                report.records.push(RecordReport {
+                    index,
+                    site: record.site.clone(),
+                    violations,
+                });
+            }
+            if !rejected {
+                records.push(checked);
+            }
+        }
+
+        Ok((dataset.with_records(records), report))
+    }
+
+    /// Validates one incoming record whose features are named by
+    /// `feature_names`. Returns the record after clipping (unchanged under
+    /// `Reject` and `Flag`) with the rules it broke; callers decide whether to
+    /// score a record with violations.
+    pub fn validate_record(
+        &self,
+        feature_names: &[String],
+        record: &ProcessedPatientRecord,
+    ) -> Result<(ProcessedPatientRecord, Vec<Violation>)> {
+        check_width(record, feature_names.len())?;
+        let mut checked = record.clone();
+        let mut violations = Vec::new();
+
+        for rule in &self.rules {
+            let column = Self::position(feature_names, rule.feature())?;
+            let value = checked.features[column];
+            if value.is_nan() {
+                continue;
+            }
+
+            let (expected, replacement) = match rule {
+                Rule::Range { min, max, .. } => {
+                    if (*min..=*max).contains(&value) {
+                        continue;
+                    }
+                    (format!("{} to {}", min, max), value.clamp(*min, *max))
+                }
+                Rule::AllowedCodes { codes, .. } => {
+                    if codes.contains(&value) {
+                        continue;
+                    }
+                    let codes: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
+                    (format!("one of {}", codes.join(", ")), f32::NAN)
+                }
+                Rule::Conditional { given, bounds, .. } => {
+                    let given_value = checked.features[Self::position(feature_names, given)?];
+                    if given_value.is_nan() {
+                        continue;
+                    }
+                    let (min, max) = bounds(given_value);
+                    if (min..=max).contains(&value) {
+                        continue;
+                    }
+                    (format!("{} to {} for {} = {}", min, max, given, given_value), value.clamp(min, max))
+                }
+            };
+
+            violations.push(Violation {
+                feature: rule.feature().to_string(),
+                value,
+                expected,
+            });
+            if self.action == ValidationAction::Clip {
+                checked.features[column] = replacement;
+            }
+        }
+
+        Ok((checked, violations))
+    }
+
+    fn position(feature_names: &[String], feature: &str) -> Result<usize> {
+        feature_names
+            .iter()
+            .position(|name| name == feature)
+            .ok_or_else(|| PredictorError::SchemaMismatch(format!("validation rule refers to unknown feature '{}'", feature)))
+    }
+}
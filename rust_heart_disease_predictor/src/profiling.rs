@@ -0,0 +1,195 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+use crate::error::Result;
+use crate::preprocessing::Dataset;
+use crate::preprocessing::scaling::quantile;
+use crate::preprocessing::schema::FeatureKind;
+
+/// Summary statistics of one feature. Missing values are counted but left out
+/// of every other statistic, which is `NaN` (`null` in JSON) when no value is
+/// present. `std` is the sample standard deviation and `skewness` the
+/// moment coefficient of skewness.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureStats {
+    pub count: usize,
+    pub missing: usize,
+    pub mean: f32,
+    pub std: f32,
+    pub min: f32,
+    pub q1: f32,
+    pub median: f32,
+    pub q3: f32,
+    pub max: f32,
+    pub distinct: usize,
+    pub skewness: f32,
+}
+
+impl FeatureStats {
+    pub fn from_values(values: &[f32]) -> Self {
+        let mut present: Vec<f32> = values.iter().copied().filter(|value| !value.is_nan()).collect();
+        present.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let count = present.len();
+        let missing = values.len() - count;
+
+        if count == 0 {
+            return FeatureStats {
+                count,
+                missing,
+                mean: f32::NAN,
+                std: f32::NAN,
+                min: f32::NAN,
+                q1: f32::NAN,
+                median: f32::NAN,
+                q3: f32::NAN,
+                max: f32::NAN,
+                distinct: 0,
+                skewness: f32::NAN,
+            };
+        }
+
+        let n = count as f32;
+        let mean = present.iter().sum::<f32>() / n;
+        let moment = |power: i32| present.iter().map(|value| (value - mean).powi(power)).sum::<f32>() / n;
+        let (m2, m3) = (moment(2), moment(3));
+        let std = if count > 1 { (m2 * n / (n - 1.0)).sqrt() } else { 0.0 };
+        let skewness = if m2 > f32::EPSILON { m3 / m2.powf(1.5) } else { 0.0 };
+
+        let mut distinct = present.clone();
+        distinct.dedup();
+
+        FeatureStats {
+            count,
+            missing,
+            mean,
+            std,
+            min: present[0],
+            q1: quantile(&present, 0.25),
+            median: quantile(&present, 0.5),
+            q3: quantile(&present, 0.75),
+            max: present[count - 1],
+            distinct: distinct.len(),
+            skewness,
+        }
+    }
+}
+
+/// Statistics of one feature over the whole dataset and within each target class.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureProfile {
+    pub name: String,
+    pub kind: FeatureKind,
+    pub overall: FeatureStats,
+    pub by_class: BTreeMap<u8, FeatureStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetProfile {
+    pub records: usize,
+    pub class_counts: BTreeMap<u8, usize>,
+    pub features: Vec<FeatureProfile>,
+}
+
+impl DatasetProfile {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+/// Summarises every feature of a dataset, overall and per target class.
+pub fn profile(dataset: &Dataset) -> DatasetProfile {
+    let mut class_counts = BTreeMap::new();
+    for record in &dataset.records {
+        *class_counts.entry(record.target).or_insert(0) += 1;
+    }
+
+    let features = dataset
+        .feature_names
+        .iter()
+        .zip(dataset.feature_kinds.iter())
+        .enumerate()
+        .map(|(i, (name, &kind))| {
+            let values: Vec<f32> = dataset.records.iter().map(|record| record.features[i]).collect();
+            let by_class = class_counts
+                .keys()
+                .map(|&class| {
+                    let class_values: Vec<f32> = dataset
+                        .records
+                        .iter()
+                        .filter(|record| record.target == class)
+                        .map(|record| record.features[i])
+                        .collect();
+                    (class, FeatureStats::from_values(&class_values))
+                })
+                .collect();
+
+            FeatureProfile {
+                name: name.clone(),
+                kind,
+                overall: FeatureStats::from_values(&values),
+                by_class,
+            }
+        })
+        .collect();
+
+    DatasetProfile {
+        records: dataset.len(),
+        class_counts,
+        features,
+    }
+}
+
+fn print_stats_table(rows: &[(&str, &FeatureStats)]) {
+    println!(
+        "| {:<10} | Count | Missing | Mean     | Std      | Min      | Q1       | Median   | Q3       | Max      | Distinct | Skew    |",
+        "Feature"
+    );
+    println!("|------------|-------|---------|----------|----------|----------|----------|----------|----------|----------|----------|---------|");
+    for (name, stats) in rows {
+        println!(
+            "| {:<10} | {:<5} | {:<7} | {:>8.2} | {:>8.2} | {:>8.2} | {:>8.2} | {:>8.2} | {:>8.2} | {:>8.2} | {:<8} | {:>7.3} |",
+            name,
+            stats.count,
+            stats.missing,
+            stats.mean,
+            stats.std,
+            stats.min,
+            stats.q1,
+            stats.median,
+            stats.q3,
+            stats.max,
+            stats.distinct,
+            stats.skewness
+        );
+    }
+    println!("|------------|-------|---------|----------|----------|----------|----------|----------|----------|----------|----------|---------|");
+}
+
+/// Prints the overall statistics of every feature, then one table per target class.
+pub fn print_profile(profile: &DatasetProfile) {
+    println!("Dataset profile ({} records)", profile.records);
+    let rows: Vec<(&str, &FeatureStats)> = profile
+        .features
+        .iter()
+        .map(|feature| (feature.name.as_str(), &feature.overall))
+        .collect();
+    print_stats_table(&rows);
+
+    for (class, count) in &profile.class_counts {
+        println!("Class {} ({} records)", class, count);
+        let rows: Vec<(&str, &FeatureStats)> = profile
+            .features
+            .iter()
+            .map(|feature| (feature.name.as_str(), &feature.by_class[class]))
+            .collect();
+        print_stats_table(&rows);
+    }
+}
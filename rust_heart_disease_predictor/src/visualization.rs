@@ -1,8 +1,10 @@
 use plotters::prelude::*;
-use crate::evaluation::Metrics;
+use crate::error::Result;
+use crate::evaluation::{ConfusionMatrix, Metrics};
+use crate::models::optimization::EpochLoss;
 use crate::preprocessing::ProcessedPatientRecord;
 
-pub fn create_performance_comparison_chart(results: &[(&str, Metrics)], output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn create_performance_comparison_chart(results: &[(&str, Metrics)], output_path: &str) -> Result<()> {
     let root = BitMapBackend::new(output_path, (1000, 600)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -64,7 +66,7 @@ pub fn create_performance_comparison_chart(results: &[(&str, Metrics)], output_p
     Ok(())
 }
 
-pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn std::error::Error>> {
+pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<()> {
     let root = BitMapBackend::new("performance_chart.png", (1024, 768)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -80,12 +82,12 @@ pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn
 
     chart
         .configure_mesh()
-        .bold_line_style(&WHITE.mix(0.3))
+        .bold_line_style(WHITE.mix(0.3))
         .y_label_style(("sans-serif", 15.0).into_font()) // Y-label font size
         .x_label_style(("sans-serif", 15.0).into_font()) // X-label font size
         .y_desc("Score")
         .x_desc("Metrics") // Added X-axis description
-        .axis_style(&BLACK.mix(0.1)) // Subtle Y-axis grid lines
+        .axis_style(BLACK.mix(0.1)) // Subtle Y-axis grid lines
         .draw()?;
 
     let colors = [
@@ -95,7 +97,7 @@ pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn
         RED.mix(0.8),
         CYAN.mix(0.8),
     ];
-    results.iter().enumerate().for_each(|(i, (name, metrics))| {
+    for (i, (name, metrics)) in results.iter().enumerate() {
         let data = [
             metrics.accuracy,
             metrics.precision,
@@ -119,8 +121,7 @@ pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn
                     color,
                 )
             })
-        )
-        .unwrap()
+        )?
         .label(*name) // Add label for the legend
         .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], colors[i].filled())); // Draw a colored rectangle in the legend
 
@@ -134,14 +135,14 @@ pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn
                 let label_point = ((x_start + x_end) / 2.0, v + 0.02); // Position slightly above the bar
                 Text::new(label_text, label_point, ("sans-serif", 12.0).into_font())
             })
-        ).unwrap();
-    });
+        )?;
+    }
 
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperLeft) // Position legend
-        .border_style(&BLACK)
-        .background_style(&WHITE.mix(0.8))
+        .border_style(BLACK)
+        .background_style(WHITE.mix(0.8))
         .draw()?;
 
     // Manually add a legend title since legend_text is not available
@@ -157,8 +158,8 @@ pub fn save_performance_chart(results: &[(&str, Metrics)]) -> Result<(), Box<dyn
     Ok(())
 }
 
-pub fn create_confusion_matrix_heatmap(name: &str, confusion_matrix: (u32, u32, u32, u32), output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let (tp, tn, fp, fn_) = confusion_matrix;
+pub fn create_confusion_matrix_heatmap(name: &str, confusion_matrix: &ConfusionMatrix, output_path: &str) -> Result<()> {
+    let k = confusion_matrix.classes.len();
     
     let root = BitMapBackend::new(output_path, (600, 400)).into_drawing_area();
     root.fill(&WHITE)?;
@@ -169,72 +170,60 @@ pub fn create_confusion_matrix_heatmap(name: &str, confusion_matrix: (u32, u32,
         .margin(10)
         .x_label_area_size(50)
         .y_label_area_size(80)
-        .build_cartesian_2d(0.0..2.0, 0.0..2.0)?;
+        .build_cartesian_2d(0.0..k as f64, 0.0..k as f64)?;
 
     chart.configure_mesh().draw()?;
 
     // Define color gradients based on values
-    let max_val = *[tp, tn, fp, fn_].iter().max().unwrap();
+    let max_val = confusion_matrix.counts.iter().flatten().copied().max().unwrap_or(0).max(1);
     let get_color = |val: u32| -> RGBColor {
         let intensity = (val as f32 / max_val as f32 * 255.0) as u8;
         RGBColor(255 - intensity, intensity, 100)
     };
 
-    // Draw heatmap cells
-    chart.draw_series(std::iter::once(Rectangle::new(
-        [(0.0, 0.0), (1.0, 1.0)],
-        get_color(tn).filled(),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Rectangle::new(
-        [(1.0, 0.0), (2.0, 1.0)],
-        get_color(fp).filled(),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Rectangle::new(
-        [(0.0, 1.0), (1.0, 2.0)],
-        get_color(fn_).filled(),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Rectangle::new(
-        [(1.0, 1.0), (2.0, 2.0)],
-        get_color(tp).filled(),
-    )))?;
+    // Predicted class along x, actual class along y
+    let font_size = if k > 2 { 20 } else { 30 };
+    for (actual, row) in confusion_matrix.counts.iter().enumerate() {
+        for (predicted, &count) in row.iter().enumerate() {
+            let (x, y) = (predicted as f64, actual as f64);
 
-    // Add text labels for values
-    chart.draw_series(std::iter::once(Text::new(
-        format!("{}", tn),
-        (0.5, 0.5),
-        ("sans-serif", 30).into_font().color(&BLACK),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Text::new(
-        format!("{}", fp),
-        (1.5, 0.5),
-        ("sans-serif", 30).into_font().color(&BLACK),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Text::new(
-        format!("{}", fn_),
-        (0.5, 1.5),
-        ("sans-serif", 30).into_font().color(&BLACK),
-    )))?;
-    
-    chart.draw_series(std::iter::once(Text::new(
-        format!("{}", tp),
-        (1.5, 1.5),
-        ("sans-serif", 30).into_font().color(&BLACK),
-    )))?;
+            // Draw heatmap cell
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(x, y), (x + 1.0, y + 1.0)],
+                get_color(count).filled(),
+            )))?;
+
+            // Add text label for the value
+            chart.draw_series(std::iter::once(Text::new(
+                format!("{}", count),
+                (x + 0.5, y + 0.5),
+                ("sans-serif", font_size).into_font().color(&BLACK),
+            )))?;
+        }
+    }
 
     // Add axis labels
-    chart.draw_series((0..2).map(|x| Text::new(
-        if x == 0 { "Predicted Neg" } else { "Predicted Pos" },
+    let labels: Vec<(String, String)> = if confusion_matrix.is_binary() {
+        vec![
+            ("Predicted Neg".to_string(), "Actual Neg".to_string()),
+            ("Predicted Pos".to_string(), "Actual Pos".to_string()),
+        ]
+    } else {
+        confusion_matrix
+            .classes
+            .iter()
+            .map(|class| (format!("Pred {}", class), format!("Actual {}", class)))
+            .collect()
+    };
+
+    chart.draw_series(labels.iter().enumerate().map(|(x, (predicted, _))| Text::new(
+        predicted.clone(),
         (x as f64 + 0.5, -0.3),
         ("sans-serif", 15).into_font(),
     )))?;
     
-    chart.draw_series((0..2).map(|y| Text::new(
-        if y == 0 { "Actual Neg" } else { "Actual Pos" },
+    chart.draw_series(labels.iter().enumerate().map(|(y, (_, actual))| Text::new(
+        actual.clone(),
         (-0.5, y as f64 + 0.5),
         ("sans-serif", 15).into_font(),
     )))?;
@@ -242,7 +231,7 @@ pub fn create_confusion_matrix_heatmap(name: &str, confusion_matrix: (u32, u32,
     Ok(())
 }
 
-pub fn create_feature_histograms(data: &[ProcessedPatientRecord], output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn create_feature_histograms(data: &[ProcessedPatientRecord], output_path: &str) -> Result<()> {
     // For simplicity, let's create a histogram for the first feature (age)
     if data.is_empty() {
         return Ok(());
@@ -252,7 +241,7 @@ pub fn create_feature_histograms(data: &[ProcessedPatientRecord], output_path: &
     feature_values.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
     // Create histogram data
-    let mut counts = vec![0; 10]; // 10 bins
+    let mut counts = [0; 10]; // 10 bins
     let min_val = feature_values[0];
     let max_val = feature_values[feature_values.len() - 1];
     let range = max_val - min_val;
@@ -289,10 +278,65 @@ pub fn create_feature_histograms(data: &[ProcessedPatientRecord], output_path: &
     Ok(())
 }
 
+/// Training loss per epoch, with the validation loss when one was recorded.
+pub fn create_loss_curve_chart(history: &[EpochLoss], output_path: &str) -> Result<()> {
+    if history.is_empty() {
+        return Ok(());
+    }
+
+    let max_loss = history
+        .iter()
+        .flat_map(|loss| std::iter::once(loss.training_loss).chain(loss.validation_loss))
+        .fold(0.0f32, f32::max);
+
+    let root = BitMapBackend::new(output_path, (800, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Logistic Regression Loss", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(80)
+        .build_cartesian_2d(0.0..history.len() as f64, 0.0..(max_loss * 1.1) as f64)?;
+
+    chart
+        .configure_mesh()
+        .x_desc("Epoch")
+        .y_desc("Loss")
+        .draw()?;
+
+    chart
+        .draw_series(LineSeries::new(
+            history.iter().map(|loss| (loss.epoch as f64, loss.training_loss as f64)),
+            &BLUE,
+        ))?
+        .label("Training")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+
+    if history.iter().any(|loss| loss.validation_loss.is_some()) {
+        chart
+            .draw_series(LineSeries::new(
+                history
+                    .iter()
+                    .filter_map(|loss| loss.validation_loss.map(|v| (loss.epoch as f64, v as f64))),
+                &RED,
+            ))?
+            .label("Validation")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+    }
 
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
 
 // Function to create correlation matrix heatmap
-pub fn create_correlation_matrix_heatmap(data: &[ProcessedPatientRecord], output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+pub fn create_correlation_matrix_heatmap(data: &[ProcessedPatientRecord], output_path: &str) -> Result<()> {
     if data.is_empty() || data[0].features.is_empty() {
         return Ok(());
     }
@@ -356,10 +400,9 @@ pub fn create_correlation_matrix_heatmap(data: &[ProcessedPatientRecord], output
     chart.configure_mesh().draw()?;
 
     // Draw correlation heatmap
-    for i in 0..num_features {
-        for j in 0..num_features {
+    for (i, row) in correlation_matrix.iter().enumerate() {
+        for (j, &corr_val) in row.iter().enumerate() {
             // Map correlation value to color (red for negative, blue for positive)
-            let corr_val = correlation_matrix[i][j];
             let (r, g, b) = if corr_val >= 0.0 {
                 // Blue scale for positive correlation
                 let intensity = (corr_val * 255.0) as u8;